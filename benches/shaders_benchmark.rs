@@ -11,7 +11,15 @@ pub fn benchmark(c: &mut Criterion) {
     let gpu_manager = gpu_manager::GpuManager::simple().block_on().unwrap();
 
     instantiation.bench_with_input("Compute Context", &gpu_manager, |b, manager| {
-        b.iter(|| ray::ComputeContext::new(manager.device(), (1920, 1080), &SPHERES));
+        b.iter(|| {
+            ray::ComputeContext::new(
+                manager.device(),
+                (1920, 1080),
+                &ray::objects::Scene::new(SPHERES.to_vec()),
+                &ray::Camera::default(),
+                &ray::RenderSettings::default(),
+            )
+        });
     });
     instantiation.finish();
 
@@ -36,7 +44,9 @@ pub fn benchmark(c: &mut Criterion) {
                                 ray::ComputeContext::new(
                                     device,
                                     *size,
-                                    std::slice::from_ref(sphere),
+                                    &ray::objects::Scene::new(vec![*sphere]),
+                                    &ray::Camera::default(),
+                                    &ray::RenderSettings::default(),
                                 )
                             },
                             |compute_ctx| {
@@ -71,7 +81,15 @@ pub fn benchmark(c: &mut Criterion) {
                 ),
                 |b, (size, device, queue, frames)| {
                     b.iter_batched(
-                        || ray::ComputeContext::new(device, *size, &SPHERES),
+                        || {
+                            ray::ComputeContext::new(
+                                device,
+                                *size,
+                                &ray::objects::Scene::new(SPHERES.to_vec()),
+                                &ray::Camera::default(),
+                                &ray::RenderSettings::default(),
+                            )
+                        },
                         |compute_ctx| {
                             for _ in 0..*frames {
                                 let mut encoder = device
@@ -87,6 +105,100 @@ pub fn benchmark(c: &mut Criterion) {
         }
     }
     complete_scene.finish();
+
+    let mut russian_roulette = c.benchmark_group("Russian Roulette");
+    for (name, depth) in [("disabled", None), ("after 3 bounces", Some(3))] {
+        for output_size in [(256, 256), (512, 512), (1920, 1080)] {
+            russian_roulette.bench_with_input(
+                format!(
+                    "Draw entire scene in resolution {output_size:?} with russian roulette {name}."
+                ),
+                &(output_size, gpu_manager.device(), gpu_manager.queue()),
+                |b, (size, device, queue)| {
+                    b.iter_batched(
+                        || {
+                            ray::ComputeContext::new(
+                                device,
+                                *size,
+                                &ray::objects::Scene::new(SPHERES.to_vec()),
+                                &ray::Camera::default(),
+                                &ray::RenderSettings {
+                                    russian_roulette: depth,
+                                    ..Default::default()
+                                },
+                            )
+                        },
+                        |compute_ctx| {
+                            let mut encoder =
+                                device.create_command_encoder(&CommandEncoderDescriptor::default());
+                            compute_ctx.draw(&mut encoder, queue);
+                            queue.submit(Some(encoder.finish()));
+                            // Wait for the GPU, so that the time spent tracing paths is measured.
+                            device.poll(wgpu::PollType::Wait).unwrap();
+                        },
+                        criterion::BatchSize::LargeInput,
+                    );
+                },
+            );
+        }
+    }
+    russian_roulette.finish();
+
+    let mut bvh = c.benchmark_group("BVH");
+    for count in [10, 1_000, 100_000] {
+        let scene = ray::objects::Scene::new(sphere_grid(count));
+        for use_bvh in [false, true] {
+            bvh.bench_with_input(
+                format!(
+                    "Draw {count} spheres in resolution (256, 256) {} the BVH.",
+                    if use_bvh { "with" } else { "without" }
+                ),
+                &(&scene, gpu_manager.device(), gpu_manager.queue()),
+                |b, (scene, device, queue)| {
+                    b.iter_batched(
+                        || {
+                            ray::ComputeContext::new(
+                                device,
+                                (256, 256),
+                                scene,
+                                &ray::Camera::default(),
+                                &ray::RenderSettings {
+                                    bvh: use_bvh,
+                                    ..Default::default()
+                                },
+                            )
+                        },
+                        |compute_ctx| {
+                            let mut encoder =
+                                device.create_command_encoder(&CommandEncoderDescriptor::default());
+                            compute_ctx.draw(&mut encoder, queue);
+                            queue.submit(Some(encoder.finish()));
+                            // Wait for the GPU, so that the time spent tracing paths is measured.
+                            device.poll(wgpu::PollType::Wait).unwrap();
+                        },
+                        criterion::BatchSize::LargeInput,
+                    );
+                },
+            );
+        }
+    }
+    bvh.finish();
+}
+
+/// `count` spheres on a cubic grid in front of the default camera.
+fn sphere_grid(count: usize) -> Vec<ray::objects::Sphere> {
+    let side = (count as f32).cbrt().ceil() as usize;
+    let spacing = 2. / side as f32;
+    (0..count)
+        .map(|i| {
+            let [x, y, z] = [i % side, i / side % side, i / (side * side)].map(|n| n as f32);
+            ray::objects::Sphere::new(
+                [x * spacing - 1., y * spacing - 1., -z * spacing - 1.5],
+                spacing * 0.4,
+                ray::objects::Material::lambertian([0.5, 0.5, 0.5]),
+            )
+        })
+        .collect()
 }
 
 criterion_group!(benches, benchmark);
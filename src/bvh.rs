@@ -0,0 +1,273 @@
+use glam::{Mat4, Vec3};
+
+// Kinds of primitives referenced by the leaves, stored in the top bits of the reference.
+pub(crate) const SPHERE: u32 = 0;
+pub(crate) const TRIANGLE: u32 = 1;
+pub(crate) const QUAD: u32 = 2;
+pub(crate) const INSTANCE: u32 = 3;
+pub(crate) const DISK: u32 = 4;
+pub(crate) const SHAPE: u32 = 5;
+const KIND_SHIFT: u32 = 28;
+// Marks nodes that are not leaves.
+const INTERIOR: u32 = u32::MAX;
+// Primitive of the leaf standing for an empty hierarchy, which is of no kind and never hit.
+const NOTHING: u32 = u32::MAX - 1;
+
+const BINS: usize = 12;
+/// Deepest a leaf may be below its root. The shader's traversal stack holds at most one node
+/// per level of the instances' hierarchy and of a geometry's, plus the instance it entered, so
+/// it must be at least twice as large plus one.
+pub(crate) const MAX_DEPTH: u32 = 31;
+
+/// Axis aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Aabb {
+    pub(crate) min: Vec3,
+    pub(crate) max: Vec3,
+}
+
+impl Aabb {
+    /// The empty box, which contains nothing and is never hit.
+    pub(crate) const EMPTY: Self = Self {
+        min: Vec3::MAX,
+        max: Vec3::MIN,
+    };
+
+    pub(crate) fn from_points(points: impl IntoIterator<Item = Vec3>) -> Self {
+        points.into_iter().fold(Self::EMPTY, |aabb, point| Self {
+            min: aabb.min.min(point),
+            max: aabb.max.max(point),
+        })
+    }
+
+    pub(crate) fn union(self, other: Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// The box inside both boxes, or the empty box when they don't overlap.
+    pub(crate) fn intersection(self, other: Self) -> Self {
+        let (min, max) = (self.min.max(other.min), self.max.min(other.max));
+        if min.cmple(max).all() {
+            Self { min, max }
+        } else {
+            Self::EMPTY
+        }
+    }
+
+    /// Bounds of the disk facing `normal`.
+    pub(crate) fn disk(center: Vec3, normal: Vec3, radius: f32) -> Self {
+        // How far the rim reaches along each axis.
+        let normal = normal.normalize_or_zero();
+        let extent = radius.abs() * (Vec3::ONE - normal * normal).max(Vec3::ZERO).map(f32::sqrt);
+        Self {
+            min: center - extent,
+            max: center + extent,
+        }
+    }
+
+    /// Bounds of the box after it's transformed by `transform`.
+    pub(crate) fn transform(self, transform: Mat4) -> Self {
+        if self == Self::EMPTY {
+            return self;
+        }
+        Self::from_points((0..8).map(|corner| {
+            let select = |bit, axis: usize| {
+                if corner & bit == 0 {
+                    self.min[axis]
+                } else {
+                    self.max[axis]
+                }
+            };
+            transform.transform_point3(Vec3::new(select(1, 0), select(2, 1), select(4, 2)))
+        }))
+    }
+
+    fn centroid(self) -> Vec3 {
+        (self.min + self.max) / 2.
+    }
+
+    fn surface_area(self) -> f32 {
+        let size = (self.max - self.min).max(Vec3::ZERO);
+        2. * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+}
+
+/// A node of the flattened hierarchy. Nodes are stored depth first, so the left child of an
+/// interior node directly follows it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct Node {
+    min: [f32; 3],
+    right_child: u32,
+    max: [f32; 3],
+    // Kind and index of the leaf's primitive, or `INTERIOR`.
+    primitive: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Primitive {
+    pub(crate) aabb: Aabb,
+    centroid: Vec3,
+    reference: u32,
+}
+
+impl Primitive {
+    pub(crate) fn new(aabb: Aabb, kind: u32, index: usize) -> Self {
+        let index = u32::try_from(index)
+            .ok()
+            .filter(|index| index >> KIND_SHIFT == 0)
+            .expect("Too many primitives for the BVH.");
+        Self {
+            aabb,
+            centroid: aabb.centroid(),
+            reference: kind << KIND_SHIFT | index,
+        }
+    }
+}
+
+/// Appends a bounding volume hierarchy over `primitives` to `nodes`, built with the binned
+/// surface area heuristic and holding a single primitive per leaf. Returns the index of its
+/// root.
+pub(crate) fn build(mut primitives: Vec<Primitive>, nodes: &mut Vec<Node>) -> u32 {
+    let root = u32::try_from(nodes.len()).expect("Too many BVH nodes.");
+    if primitives.is_empty() {
+        nodes.push(Node::empty());
+    } else {
+        nodes.reserve(2 * primitives.len() - 1);
+        build_node(&mut primitives, nodes, 0);
+    }
+    root
+}
+
+fn build_node(primitives: &mut [Primitive], nodes: &mut Vec<Node>, depth: u32) {
+    if let [primitive] = primitives {
+        nodes.push(Node::leaf(primitive));
+        return;
+    }
+
+    let aabb = primitives
+        .iter()
+        .fold(Aabb::EMPTY, |aabb, primitive| aabb.union(primitive.aabb));
+    let index = nodes.len();
+    nodes.push(Node::interior(aabb));
+
+    // Halving the primitives keeps the leaves within `MAX_DEPTH`, whereas the surface area
+    // heuristic may only split off a few at a time.
+    let balanced_depth = depth + primitives.len().next_power_of_two().trailing_zeros();
+    let middle = if balanced_depth < MAX_DEPTH {
+        split(primitives)
+    } else {
+        split_in_halves(primitives)
+    };
+    let (left, right) = primitives.split_at_mut(middle);
+    build_node(left, nodes, depth + 1);
+    nodes[index].right_child = nodes.len() as u32;
+    build_node(right, nodes, depth + 1);
+}
+
+/// Partitions `primitives` at the cheapest split according to the surface area heuristic,
+/// returning the number of primitives on the left.
+fn split(primitives: &mut [Primitive]) -> usize {
+    let centroids = Aabb::from_points(primitives.iter().map(|primitive| primitive.centroid));
+    let extent = centroids.max - centroids.min;
+    let bin = |primitive: &Primitive, axis: usize| {
+        let offset = (primitive.centroid[axis] - centroids.min[axis]) / extent[axis];
+        ((offset * BINS as f32) as usize).min(BINS - 1)
+    };
+
+    let mut best = None;
+    for axis in (0..3).filter(|&axis| extent[axis] > 0.) {
+        let mut bins = [(Aabb::EMPTY, 0); BINS];
+        for primitive in primitives.iter() {
+            let (aabb, count) = &mut bins[bin(primitive, axis)];
+            *aabb = aabb.union(primitive.aabb);
+            *count += 1;
+        }
+
+        // Cost of the primitives left of each split, then add the ones right of it.
+        let mut costs = [0.; BINS - 1];
+        let (mut aabb, mut count) = (Aabb::EMPTY, 0);
+        for (cost, (bin_aabb, bin_count)) in costs.iter_mut().zip(&bins) {
+            (aabb, count) = (aabb.union(*bin_aabb), count + bin_count);
+            *cost = aabb.surface_area() * count as f32;
+        }
+        let (mut aabb, mut count) = (Aabb::EMPTY, 0);
+        for (cost, (bin_aabb, bin_count)) in costs.iter_mut().zip(&bins[1..]).rev() {
+            (aabb, count) = (aabb.union(*bin_aabb), count + bin_count);
+            *cost += aabb.surface_area() * count as f32;
+        }
+
+        for (split, &cost) in costs.iter().enumerate() {
+            if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                best = Some((axis, split, cost));
+            }
+        }
+    }
+
+    let middle = match best {
+        Some((axis, split, _)) => partition(primitives, |primitive| bin(primitive, axis) <= split),
+        None => 0,
+    };
+    if middle == 0 || middle == primitives.len() {
+        // The centroids are too close to be binned, split them in two halves instead.
+        return split_in_halves(primitives);
+    }
+    middle
+}
+
+/// Partitions `primitives` in two halves along the longest axis of their centroids, returning
+/// the number of primitives on the left.
+fn split_in_halves(primitives: &mut [Primitive]) -> usize {
+    let centroids = Aabb::from_points(primitives.iter().map(|primitive| primitive.centroid));
+    let (middle, axis) = (
+        primitives.len() / 2,
+        (centroids.max - centroids.min).max_position(),
+    );
+    primitives.select_nth_unstable_by(middle, |a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+    middle
+}
+
+/// Moves the primitives matching `predicate` to the start, returning how many there are.
+fn partition(primitives: &mut [Primitive], predicate: impl Fn(&Primitive) -> bool) -> usize {
+    let mut middle = 0;
+    for i in 0..primitives.len() {
+        if predicate(&primitives[i]) {
+            primitives.swap(i, middle);
+            middle += 1;
+        }
+    }
+    middle
+}
+
+impl Node {
+    fn interior(aabb: Aabb) -> Self {
+        Self {
+            min: aabb.min.into(),
+            right_child: 0,
+            max: aabb.max.into(),
+            primitive: INTERIOR,
+        }
+    }
+
+    /// A leaf without a primitive, whose empty box may still pass the shader's slab test.
+    fn empty() -> Self {
+        Self {
+            min: Aabb::EMPTY.min.into(),
+            right_child: 0,
+            max: Aabb::EMPTY.max.into(),
+            primitive: NOTHING,
+        }
+    }
+
+    fn leaf(primitive: &Primitive) -> Self {
+        Self {
+            min: primitive.aabb.min.into(),
+            right_child: 0,
+            max: primitive.aabb.max.into(),
+            primitive: primitive.reference,
+        }
+    }
+}
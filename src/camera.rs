@@ -0,0 +1,187 @@
+use glam::Vec3;
+
+/// How rays leave the camera.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Projection {
+    /// Pinhole (or thin lens) perspective, using the camera's `vfov`.
+    #[default]
+    Perspective,
+    /// Parallel rays through a viewport `height` world units tall.
+    Orthographic { height: f32 },
+    /// Equidistant fisheye, where `vfov` is the angle covered by the height of the image. It
+    /// can go up to 360°.
+    Fisheye,
+    /// Full 360° by 180° panorama around the camera.
+    Equirectangular,
+}
+
+impl Projection {
+    const fn id(self) -> u32 {
+        match self {
+            Self::Perspective => 0,
+            Self::Orthographic { .. } => 1,
+            Self::Fisheye => 2,
+            Self::Equirectangular => 3,
+        }
+    }
+}
+
+/// A look-at camera, uploaded to the compute shader as a uniform.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub look_from: [f32; 3],
+    pub look_at: [f32; 3],
+    pub up: [f32; 3],
+    /// Vertical field of view, in degrees.
+    pub vfov: f32,
+    /// Viewport width over height. When `None`, the aspect ratio of the output is used.
+    pub aspect_ratio: Option<f32>,
+    /// Diameter of the lens. Zero gives a pinhole camera with everything in focus.
+    pub aperture: f32,
+    /// Distance from `look_from` to the plane in perfect focus. When `None`, `look_at` is in focus.
+    pub focus_distance: Option<f32>,
+    /// Number of diaphragm blades shaping the aperture. Fewer than 3 gives a circular aperture.
+    pub aperture_blades: u32,
+    /// Rotation of the aperture polygon, in degrees.
+    pub aperture_rotation: f32,
+    /// Time at which the shutter opens. Each ray is shot at a random time while it is open.
+    /// Moving spheres only move between times 0 and 1.
+    pub shutter_open: f32,
+    /// Time at which the shutter closes.
+    pub shutter_close: f32,
+    pub projection: Projection,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new([0., 0., 0.], [0., 0., -1.], [0., 1., 0.], 90.)
+    }
+}
+
+impl Camera {
+    #[must_use]
+    pub const fn new(look_from: [f32; 3], look_at: [f32; 3], up: [f32; 3], vfov: f32) -> Self {
+        Self {
+            look_from,
+            look_at,
+            up,
+            vfov,
+            aspect_ratio: None,
+            aperture: 0.,
+            focus_distance: None,
+            aperture_blades: 0,
+            aperture_rotation: 0.,
+            shutter_open: 0.,
+            shutter_close: 1.,
+            projection: Projection::Perspective,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_aspect_ratio(mut self, aspect_ratio: f32) -> Self {
+        self.aspect_ratio = Some(aspect_ratio);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_defocus(mut self, aperture: f32, focus_distance: f32) -> Self {
+        self.aperture = aperture;
+        self.focus_distance = Some(focus_distance);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_aperture_blades(mut self, blades: u32, rotation: f32) -> Self {
+        self.aperture_blades = blades;
+        self.aperture_rotation = rotation;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_shutter(mut self, open: f32, close: f32) -> Self {
+        self.shutter_open = open;
+        self.shutter_close = close;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    pub(crate) fn uniform(&self, output_size: (u32, u32)) -> CameraUniform {
+        let (width, height) = (output_size.0 as f32, output_size.1 as f32);
+
+        let center = Vec3::from(self.look_from);
+        let focal_length = self
+            .focus_distance
+            .unwrap_or_else(|| (center - Vec3::from(self.look_at)).length());
+
+        let viewport_height = match self.projection {
+            Projection::Orthographic { height } => height,
+            _ => 2. * (self.vfov.to_radians() / 2.).tan() * focal_length,
+        };
+        let viewport_width = viewport_height * self.aspect_ratio.unwrap_or(width / height);
+
+        // Orthonormal basis for the camera frame.
+        let w = (center - Vec3::from(self.look_at)).normalize();
+        let u = Vec3::from(self.up).cross(w).normalize();
+        let v = w.cross(u);
+
+        let viewport_u = viewport_width * u;
+        let viewport_v = viewport_height * -v;
+
+        let pixel_delta_u = viewport_u / width;
+        let pixel_delta_v = viewport_v / height;
+
+        let viewport_upper_left = center - focal_length * w - viewport_u / 2. - viewport_v / 2.;
+        let pix0_coord = viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v);
+
+        let defocus_radius = self.aperture / 2.;
+
+        CameraUniform {
+            center: center.into(),
+            shutter_open: self.shutter_open,
+            pix0_coord: pix0_coord.into(),
+            shutter_close: self.shutter_close,
+            pixel_delta_u: pixel_delta_u.into(),
+            projection: self.projection.id(),
+            pixel_delta_v: pixel_delta_v.into(),
+            padding: 0.,
+            defocus_disk_u: (u * defocus_radius).into(),
+            aperture_blades: self.aperture_blades,
+            defocus_disk_v: (v * defocus_radius).into(),
+            aperture_rotation: self.aperture_rotation.to_radians(),
+            forward: (-focal_length * w).into(),
+            fov: self.vfov.to_radians(),
+            right: u.into(),
+            image_width: width,
+            up: v.into(),
+            image_height: height,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct CameraUniform {
+    center: [f32; 3],
+    shutter_open: f32,
+    pix0_coord: [f32; 3],
+    shutter_close: f32,
+    pixel_delta_u: [f32; 3],
+    projection: u32,
+    pixel_delta_v: [f32; 3],
+    padding: f32,
+    defocus_disk_u: [f32; 3],
+    aperture_blades: u32,
+    defocus_disk_v: [f32; 3],
+    aperture_rotation: f32,
+    forward: [f32; 3],
+    fov: f32,
+    right: [f32; 3],
+    image_width: f32,
+    up: [f32; 3],
+    image_height: f32,
+}
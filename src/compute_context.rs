@@ -1,5 +1,9 @@
-use std::sync::{Arc, atomic::AtomicU32};
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicBool, AtomicU32},
+};
 
+use image::{RgbaImage, imageops::FilterType};
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, Buffer, BufferUsages, CommandEncoder, ComputePassDescriptor,
@@ -9,24 +13,45 @@ use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
 };
 
-use crate::objects;
+use crate::{Camera, RenderSettings, objects};
+
+// First binding of the geometry storage buffers in the settings bind group.
+const GEOMETRY_BINDING: u32 = 4;
 
 #[derive(Debug)]
 pub struct ComputeContext {
     pub(crate) compute_pipeline: ComputePipeline,
 
+    // Linear running sum of the samples in rgb and the number of samples in alpha.
     pub(crate) previous_texture: Texture,
     pub(crate) output_texture: Texture,
     pub(crate) textures_bind_groups: [BindGroup; 2],
 
     pub(crate) frame: Arc<AtomicU32>,
+    // Set when the camera or the settings change, so that the next draw restarts from the
+    // first frame.
+    reset: AtomicBool,
     pub(crate) frame_uniform: Buffer,
+    pub(crate) camera_uniform: Buffer,
+    pub(crate) render_settings_uniform: Buffer,
     pub(crate) settings_bind_group: BindGroup,
+
+    // Layers of the scene's images, all stretched to the size of the largest one.
+    images: Texture,
+    // Images written to their layers by the first draw, which has the queue.
+    images_upload: Mutex<Vec<RgbaImage>>,
+    images_bind_group: BindGroup,
 }
 
 impl ComputeContext {
-    pub fn new(device: &Device, output_size: (u32, u32), spheres: &[objects::Sphere]) -> Self {
-        let output_format = TextureFormat::Rgba8Unorm;
+    pub fn new(
+        device: &Device,
+        output_size: (u32, u32),
+        scene: &objects::Scene,
+        camera: &Camera,
+        render_settings: &RenderSettings,
+    ) -> Self {
+        let output_format = TextureFormat::Rgba32Float;
         let texture_size = Extent3d {
             width: output_size.0,
             height: output_size.1,
@@ -38,42 +63,80 @@ impl ComputeContext {
         let previous_texture = Self::create_texture(device, texture_size, output_format);
         let previous_texture_view = previous_texture.create_view(&TextureViewDescriptor::default());
 
+        // Sum of the squared luminance of the samples, used to estimate each pixel's variance.
+        let moments_format = TextureFormat::R32Float;
+        let moments_views = [(); 2].map(|()| {
+            Self::create_texture(device, texture_size, moments_format)
+                .create_view(&TextureViewDescriptor::default())
+        });
+
         let textures_bind_group_layout =
-            Self::create_textures_bind_group_layout(device, output_format);
+            Self::create_textures_bind_group_layout(device, output_format, moments_format);
 
         let textures_bind_groups = [
-            (&output_texture_view, &previous_texture_view),
-            (&previous_texture_view, &output_texture_view),
+            (&output_texture_view, &previous_texture_view, 0),
+            (&previous_texture_view, &output_texture_view, 1),
         ]
-        .map(|(write_to, read_from)| {
+        .map(|(write_to, read_from, moments)| {
             Self::create_textures_bind_group(
                 device,
                 &textures_bind_group_layout,
-                write_to,
-                read_from,
+                (write_to, &moments_views[moments]),
+                (read_from, &moments_views[1 - moments]),
             )
         });
 
-        let sphere_buffer = Self::create_sphere_buffer(device, spheres);
+        let buffers = scene.buffers();
+        let sphere_buffer = Self::create_storage_buffer(device, "Spheres Buffer", &buffers.spheres);
+        // Bound after the uniforms, in the order main.wgsl declares them.
+        let geometry_buffers = [
+            Self::create_storage_buffer(device, "Vertices Buffer", &buffers.vertices),
+            Self::create_storage_buffer(device, "Triangles Buffer", &buffers.triangles),
+            Self::create_storage_buffer(device, "BVH Buffer", &buffers.bvh),
+            Self::create_storage_buffer(device, "Quads Buffer", &buffers.quads),
+            Self::create_storage_buffer(device, "Instances Buffer", &buffers.instances),
+            Self::create_storage_buffer(device, "Disks Buffer", &buffers.disks),
+            Self::create_storage_buffer(device, "Shapes Buffer", &buffers.shapes),
+        ];
         let frame_uniform = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("Frame Uniform"),
             // Uniform buffers must be aligned to 16 bytes
             contents: &0u128.to_be_bytes(),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
+        let camera_uniform = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Camera Uniform"),
+            contents: bytemuck::bytes_of(&camera.uniform(output_size)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let render_settings_uniform = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Render Settings Uniform"),
+            contents: bytemuck::bytes_of(&render_settings.uniform()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
 
-        let settings_bind_group_layout = Self::create_settings_layout(device);
+        let settings_bind_group_layout =
+            Self::create_settings_layout(device, geometry_buffers.len());
         let settings_bind_group = Self::create_settings_bind_group(
             device,
             &settings_bind_group_layout,
             &sphere_buffer,
+            &geometry_buffers,
             &frame_uniform,
+            &camera_uniform,
+            &render_settings_uniform,
         );
 
+        let (images, images_upload) = Self::create_images(device, &scene.images);
+        let images_bind_group_layout = Self::create_images_layout(device);
+        let images_bind_group =
+            Self::create_images_bind_group(device, &images_bind_group_layout, &images);
+
         let compute_pipeline = Self::create_compute_pipeline(
             device,
             &textures_bind_group_layout,
             &settings_bind_group_layout,
+            &images_bind_group_layout,
         );
 
         Self {
@@ -82,21 +145,78 @@ impl ComputeContext {
             textures_bind_groups,
             previous_texture,
             frame: Arc::new(AtomicU32::new(0)),
+            reset: AtomicBool::new(false),
             frame_uniform,
+            camera_uniform,
+            render_settings_uniform,
             settings_bind_group,
+            images,
+            images_upload: Mutex::new(images_upload),
+            images_bind_group,
         }
     }
 
+    /// Uploads a new camera and restarts the accumulation from the first frame.
+    pub fn set_camera(&self, queue: &Queue, camera: &Camera) {
+        let output_size = (self.output_texture.width(), self.output_texture.height());
+        queue.write_buffer(
+            &self.camera_uniform,
+            0,
+            bytemuck::bytes_of(&camera.uniform(output_size)),
+        );
+        self.reset.store(true, std::sync::atomic::Ordering::Release);
+    }
+
+    /// Uploads new render settings and restarts the accumulation from the first frame.
+    pub fn set_render_settings(&self, queue: &Queue, render_settings: &RenderSettings) {
+        queue.write_buffer(
+            &self.render_settings_uniform,
+            0,
+            bytemuck::bytes_of(&render_settings.uniform()),
+        );
+        self.reset.store(true, std::sync::atomic::Ordering::Release);
+    }
+
     pub fn draw(&self, encoder: &mut CommandEncoder, queue: &Queue) {
-        let frame = self
-            .frame
-            .fetch_add(1, std::sync::atomic::Ordering::Release);
+        let frame = if self.reset.swap(false, std::sync::atomic::Ordering::AcqRel) {
+            self.frame.store(1, std::sync::atomic::Ordering::Release);
+            0
+        } else {
+            self.frame
+                .fetch_add(1, std::sync::atomic::Ordering::Release)
+        };
         queue.write_buffer(
             &self.frame_uniform,
             0,
             // Uniform buffers must be aligned to 16 bytes
             &(frame as u128).to_le_bytes(),
         );
+        // One layer at a time, as all of them may not fit in a single staging buffer.
+        for (layer, image) in (0..).zip(self.images_upload.lock().unwrap().drain(..)) {
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &self.images,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                image.as_raw(),
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * image.width()),
+                    rows_per_image: None,
+                },
+                Extent3d {
+                    width: image.width(),
+                    height: image.height(),
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
 
         {
             let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
@@ -111,6 +231,7 @@ impl ComputeContext {
                 &[],
             );
             compute_pass.set_bind_group(1, &self.settings_bind_group, &[]);
+            compute_pass.set_bind_group(2, &self.images_bind_group, &[]);
             compute_pass.dispatch_workgroups(
                 self.output_texture.width() / 8 + 1,
                 self.output_texture.height() / 8 + 1,
@@ -138,9 +259,65 @@ impl ComputeContext {
         })
     }
 
+    /// Creates the texture array holding the images, and returns the images resized to fit its
+    /// layers.
+    fn create_images(device: &Device, images: &[RgbaImage]) -> (Texture, Vec<RgbaImage>) {
+        let limits = device.limits();
+        let mut images = images;
+        if images.len() > limits.max_texture_array_layers as usize {
+            log::warn!(
+                "Only {} of the {} images fit in a texture array, the others are left out.",
+                limits.max_texture_array_layers,
+                images.len()
+            );
+            images = &images[..limits.max_texture_array_layers as usize];
+        }
+        // Textures can't be empty, so at least a pixel nothing references is allocated.
+        let size = |dimension: fn(&RgbaImage) -> u32| {
+            let size = images.iter().map(dimension).max().unwrap_or(1);
+            if size > limits.max_texture_dimension_2d {
+                log::warn!(
+                    "Images are shrunk to {} pixels, the largest texture allowed.",
+                    limits.max_texture_dimension_2d
+                );
+            }
+            size.min(limits.max_texture_dimension_2d)
+        };
+        let (width, height) = (size(RgbaImage::width), size(RgbaImage::height));
+        let layers = u32::try_from(images.len().max(1)).expect("Too many images.");
+
+        let images = images
+            .iter()
+            .map(|image| {
+                if image.dimensions() == (width, height) {
+                    image.clone()
+                } else {
+                    image::imageops::resize(image, width, height, FilterType::Triangle)
+                }
+            })
+            .collect();
+
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Images"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: layers,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        (texture, images)
+    }
+
     fn create_textures_bind_group_layout(
         device: &Device,
         format: TextureFormat,
+        moments_format: TextureFormat,
     ) -> BindGroupLayout {
         device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: Some("Compute BindGroupLayout"),
@@ -165,6 +342,26 @@ impl ComputeContext {
                     },
                     count: None,
                 },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: moments_format,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
             ],
         })
     }
@@ -172,8 +369,8 @@ impl ComputeContext {
     fn create_textures_bind_group(
         device: &Device,
         layout: &BindGroupLayout,
-        output_texture_view: &TextureView,
-        previous_texture_view: &TextureView,
+        (output_texture_view, output_moments_view): (&TextureView, &TextureView),
+        (previous_texture_view, previous_moments_view): (&TextureView, &TextureView),
     ) -> BindGroup {
         device.create_bind_group(&BindGroupDescriptor {
             label: Some("Compute BindGroup"),
@@ -187,11 +384,79 @@ impl ComputeContext {
                     binding: 1,
                     resource: wgpu::BindingResource::TextureView(previous_texture_view),
                 },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(output_moments_view),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(previous_moments_view),
+                },
+            ],
+        })
+    }
+
+    fn create_images_layout(device: &Device) -> BindGroupLayout {
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Images BindGroupLayout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    fn create_images_bind_group(
+        device: &Device,
+        layout: &BindGroupLayout,
+        images: &Texture,
+    ) -> BindGroup {
+        // A single layer would otherwise be viewed as a 2D texture.
+        let view = images.create_view(&TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        // Textures repeat outside of the unit square.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Images Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Images"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
             ],
         })
     }
 
-    fn create_settings_layout(device: &Device) -> BindGroupLayout {
+    fn create_settings_layout(device: &Device, geometry_buffers: usize) -> BindGroupLayout {
         device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: Some("Compute BindGroupLayout"),
             entries: &[
@@ -217,7 +482,42 @@ impl ComputeContext {
                     },
                     count: None,
                 },
-            ],
+                // Camera
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Render settings
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ]
+            .into_iter()
+            // Geometry
+            .chain((0..geometry_buffers).map(|i| BindGroupLayoutEntry {
+                binding: GEOMETRY_BINDING + i as u32,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }))
+            .collect::<Vec<_>>(),
         })
     }
 
@@ -225,7 +525,10 @@ impl ComputeContext {
         device: &Device,
         layout: &BindGroupLayout,
         sphere_buffer: &Buffer,
+        geometry_buffers: &[Buffer],
         frame_uniform: &Buffer,
+        camera_uniform: &Buffer,
+        render_settings_uniform: &Buffer,
     ) -> BindGroup {
         device.create_bind_group(&BindGroupDescriptor {
             label: Some("Settings"),
@@ -239,7 +542,26 @@ impl ComputeContext {
                     binding: 1,
                     resource: sphere_buffer.as_entire_binding(),
                 },
-            ],
+                BindGroupEntry {
+                    binding: 2,
+                    resource: camera_uniform.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: render_settings_uniform.as_entire_binding(),
+                },
+            ]
+            .into_iter()
+            .chain(
+                geometry_buffers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, buffer)| BindGroupEntry {
+                        binding: GEOMETRY_BINDING + i as u32,
+                        resource: buffer.as_entire_binding(),
+                    }),
+            )
+            .collect::<Vec<_>>(),
         })
     }
 
@@ -247,6 +569,7 @@ impl ComputeContext {
         device: &Device,
         textures_bind_group_layout: &BindGroupLayout,
         settings_bind_group_layout: &BindGroupLayout,
+        images_bind_group_layout: &BindGroupLayout,
     ) -> ComputePipeline {
         let shader = device.create_shader_module(ShaderModuleDescriptor {
             label: Some("shader"),
@@ -254,12 +577,21 @@ impl ComputeContext {
                 concat!(
                     include_str!("shaders/compute/math.wgsl"),
                     include_str!("shaders/compute/random.wgsl"),
+                    include_str!("shaders/compute/sampler.wgsl"),
                     include_str!("shaders/compute/interval.wgsl"),
                     include_str!("shaders/compute/sphere.wgsl"),
+                    include_str!("shaders/compute/triangle.wgsl"),
+                    include_str!("shaders/compute/quad.wgsl"),
+                    include_str!("shaders/compute/disk.wgsl"),
+                    include_str!("shaders/compute/shape.wgsl"),
+                    include_str!("shaders/compute/instance.wgsl"),
+                    include_str!("shaders/compute/bvh.wgsl"),
                     include_str!("shaders/compute/ray.wgsl"),
                     include_str!("shaders/compute/hit_record.wgsl"),
                     include_str!("shaders/compute/material.wgsl"),
+                    include_str!("shaders/compute/texture.wgsl"),
                     include_str!("shaders/compute/camera.wgsl"),
+                    include_str!("shaders/compute/settings.wgsl"),
                     include_str!("shaders/compute/main.wgsl")
                 )
                 .into(),
@@ -268,7 +600,11 @@ impl ComputeContext {
 
         let compute_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: Some("Compute Pipeline Layout"),
-            bind_group_layouts: &[textures_bind_group_layout, settings_bind_group_layout],
+            bind_group_layouts: &[
+                textures_bind_group_layout,
+                settings_bind_group_layout,
+                images_bind_group_layout,
+            ],
             push_constant_ranges: &[],
         });
 
@@ -282,11 +618,19 @@ impl ComputeContext {
         })
     }
 
-    fn create_sphere_buffer(device: &Device, spheres: &[objects::Sphere]) -> Buffer {
+    fn create_storage_buffer<T: bytemuck::Pod>(
+        device: &Device,
+        label: &str,
+        objects: &[T],
+    ) -> Buffer {
+        // Bindings can't be empty, so empty buffers hold a zeroed object nothing references.
+        let zeroed = [T::zeroed()];
+        let objects = if objects.is_empty() { &zeroed } else { objects };
+
         device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Objects Buffer"),
+            label: Some(label),
             usage: BufferUsages::STORAGE,
-            contents: bytemuck::cast_slice(spheres),
+            contents: bytemuck::cast_slice(objects),
         })
     }
 }
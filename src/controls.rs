@@ -0,0 +1,144 @@
+use glam::{Quat, Vec3};
+use winit::{
+    event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+use crate::Camera;
+
+const MOVE_SPEED: f32 = 1.5;
+const LOOK_SENSITIVITY: f32 = 0.003;
+const ZOOM_STEP: f32 = 2.;
+const MIN_VFOV: f32 = 1.;
+const MAX_VFOV: f32 = 170.;
+// Keeps the view direction from becoming parallel to the up vector.
+const MAX_PITCH_COS: f32 = 0.99;
+
+/// Fly-through controls: WASD to move, Q/E to go down/up, drag with the left mouse
+/// button to look around and scroll to change the field of view.
+#[derive(Debug, Default)]
+pub(crate) struct CameraController {
+    forward: bool,
+    backward: bool,
+    left: bool,
+    right: bool,
+    up: bool,
+    down: bool,
+    looking: bool,
+    cursor: Option<(f64, f64)>,
+}
+
+impl CameraController {
+    /// Handles a window event, returning whether `camera` changed.
+    pub(crate) fn process_event(&mut self, event: &WindowEvent, camera: &mut Camera) -> bool {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                if let PhysicalKey::Code(key) = event.physical_key {
+                    self.process_keyboard(key, event.state);
+                }
+                false
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.looking = state.is_pressed();
+                false
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let previous = self.cursor.replace((position.x, position.y));
+                match previous {
+                    Some((x, y)) if self.looking => {
+                        self.process_mouse_motion(position.x - x, position.y - y, camera)
+                    }
+                    _ => false,
+                }
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor = None;
+                false
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.,
+                };
+                self.process_scroll(lines, camera)
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn process_keyboard(&mut self, key: KeyCode, state: ElementState) {
+        let pressed = state.is_pressed();
+        match key {
+            KeyCode::KeyW => self.forward = pressed,
+            KeyCode::KeyS => self.backward = pressed,
+            KeyCode::KeyA => self.left = pressed,
+            KeyCode::KeyD => self.right = pressed,
+            KeyCode::KeyE => self.up = pressed,
+            KeyCode::KeyQ => self.down = pressed,
+            _ => (),
+        }
+    }
+
+    pub(crate) fn process_mouse_motion(&mut self, dx: f64, dy: f64, camera: &mut Camera) -> bool {
+        if dx == 0. && dy == 0. {
+            return false;
+        }
+
+        let (look_from, direction, up) = Self::frame(camera);
+        let right = direction.cross(up).normalize();
+
+        let yaw = Quat::from_axis_angle(up, -dx as f32 * LOOK_SENSITIVITY);
+        let pitch = Quat::from_axis_angle(right, -dy as f32 * LOOK_SENSITIVITY);
+
+        let pitched = pitch * direction;
+        let new_direction = if pitched.normalize().dot(up).abs() < MAX_PITCH_COS {
+            yaw * pitched
+        } else {
+            yaw * direction
+        };
+
+        camera.look_at = (look_from + new_direction).into();
+        true
+    }
+
+    pub(crate) fn process_scroll(&mut self, lines: f32, camera: &mut Camera) -> bool {
+        let vfov = (camera.vfov - lines * ZOOM_STEP).clamp(MIN_VFOV, MAX_VFOV);
+        let changed = vfov != camera.vfov;
+        camera.vfov = vfov;
+        changed
+    }
+
+    /// Moves `camera` according to the keys held down for `dt` seconds, returning
+    /// whether it changed.
+    pub(crate) fn update(&self, camera: &mut Camera, dt: f32) -> bool {
+        let axis = |positive: bool, negative: bool| f32::from(positive) - f32::from(negative);
+        let (forward, right, up) = (
+            axis(self.forward, self.backward),
+            axis(self.right, self.left),
+            axis(self.up, self.down),
+        );
+        if forward == 0. && right == 0. && up == 0. {
+            return false;
+        }
+
+        let (look_from, direction, world_up) = Self::frame(camera);
+        let forward_dir = direction.normalize();
+        let right_dir = forward_dir.cross(world_up).normalize();
+
+        let offset = (forward * forward_dir + right * right_dir + up * world_up) * MOVE_SPEED * dt;
+
+        camera.look_from = (look_from + offset).into();
+        camera.look_at = (look_from + direction + offset).into();
+        true
+    }
+
+    fn frame(camera: &Camera) -> (Vec3, Vec3, Vec3) {
+        let look_from = Vec3::from(camera.look_from);
+        let direction = Vec3::from(camera.look_at) - look_from;
+        (look_from, direction, Vec3::from(camera.up).normalize())
+    }
+}
@@ -1,42 +1,71 @@
-use std::path::Path;
+use std::{path::Path, time::Instant};
 
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
+use controls::CameraController;
 use gpu_manager::GpuManager;
 use log::info;
 use pollster::FutureExt;
 pub use render_context::RenderContext;
 use renderer::Renderer;
 use wgpu::{CommandEncoderDescriptor, Texture};
-use winit::{application::ApplicationHandler, event::WindowEvent};
-
+use winit::{
+    application::ApplicationHandler,
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+mod bvh;
+mod camera;
+pub use camera::{Camera, Projection};
 mod compute_context;
 pub use compute_context::ComputeContext;
+mod controls;
 mod render_context;
+mod render_settings;
+pub use render_settings::{AdaptiveSampling, Background, RenderSettings, Sampler};
 
 pub mod objects;
 pub mod renderer;
 
+// Instances are placed with glam matrices.
+pub use glam;
+// Textures are made of image buffers.
+pub use image;
+
 #[cfg(test)]
 mod tests;
 
 pub struct App<'window> {
     renderer: Option<Renderer<'window>>,
-    spheres: Vec<objects::Sphere>,
+    scene: objects::Scene,
+    camera: Camera,
+    render_settings: RenderSettings,
+    controller: CameraController,
+    last_update: Instant,
+    // Whether the samples per pixel are shown instead of the image, toggled with H.
+    heatmap: bool,
 }
 
 impl App<'_> {
     #[must_use]
-    pub fn new(spheres: Vec<objects::Sphere>) -> Self {
+    pub fn new(scene: objects::Scene, camera: Camera, render_settings: RenderSettings) -> Self {
         Self {
             renderer: None,
-            spheres,
+            scene,
+            camera,
+            render_settings,
+            controller: CameraController::default(),
+            last_update: Instant::now(),
+            heatmap: false,
         }
     }
 }
 
 impl ApplicationHandler for App<'_> {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
-        self.renderer = Some(Renderer::new(event_loop, &self.spheres));
+        let renderer = Renderer::new(event_loop, &self.scene, &self.camera, &self.render_settings);
+        renderer.set_heatmap(self.heatmap);
+        self.renderer = Some(renderer);
     }
 
     fn window_event(
@@ -58,15 +87,42 @@ impl ApplicationHandler for App<'_> {
                     return;
                 };
 
-                if renderer.frame() == 0 {
-                    return;
+                let dt = self.last_update.elapsed().as_secs_f32();
+                self.last_update = Instant::now();
+                if self.controller.update(&mut self.camera, dt) {
+                    renderer.set_camera(&self.camera);
                 }
 
-                renderer.render();
+                // Wait for the compute thread to produce the first frame.
+                if renderer.frame() != 0 {
+                    renderer.render();
+                }
                 renderer.window_manager().window().request_redraw();
             }
 
-            _ => (),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::KeyH),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                self.heatmap = !self.heatmap;
+                if let Some(renderer) = self.renderer.as_ref() {
+                    renderer.set_heatmap(self.heatmap);
+                }
+            }
+
+            event => {
+                if self.controller.process_event(&event, &mut self.camera)
+                    && let Some(renderer) = self.renderer.as_ref()
+                {
+                    renderer.set_camera(&self.camera);
+                }
+            }
         }
     }
 }
@@ -76,9 +132,14 @@ pub fn write_to_file<SurfaceManager>(
     texture: &Texture,
     path: Option<&Path>,
 ) -> Result<()> {
-    const U32_SIZE: u32 = std::mem::size_of::<u32>() as u32;
+    let bytes_per_pixel = texture
+        .format()
+        .block_copy_size(None)
+        .context("Texture format can't be copied to a buffer.")?;
+    let unpadded_bytes_per_row = bytes_per_pixel * texture.width();
+    let bytes_per_row = unpadded_bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
 
-    let output_buffer_size = (U32_SIZE * texture.width() * texture.height()) as wgpu::BufferAddress;
+    let output_buffer_size = (bytes_per_row * texture.height()) as wgpu::BufferAddress;
 
     let output_buffer_desc = wgpu::BufferDescriptor {
         label: Some("Output Buffer"),
@@ -104,7 +165,7 @@ pub fn write_to_file<SurfaceManager>(
             buffer: &output_buffer,
             layout: wgpu::TexelCopyBufferLayout {
                 offset: 0,
-                bytes_per_row: Some(U32_SIZE * texture.width()),
+                bytes_per_row: Some(bytes_per_row),
                 rows_per_image: Some(texture.height()),
             },
         },
@@ -125,10 +186,22 @@ pub fn write_to_file<SurfaceManager>(
         rx.receive().block_on();
 
         let data = buffer_slice.get_mapped_range();
+        let rows = data
+            .chunks_exact(bytes_per_row as usize)
+            .map(|row| &row[..unpadded_bytes_per_row as usize]);
+
+        let pixels: Vec<u8> = match texture.format() {
+            wgpu::TextureFormat::Rgba8Unorm => rows.flatten().copied().collect(),
+            wgpu::TextureFormat::Rgba32Float => rows
+                .flat_map(|row| row.chunks_exact(16))
+                .flat_map(|texel| resolve_accumulated(bytemuck::pod_read_unaligned(texel)))
+                .collect(),
+            format => bail!("Can't save textures with format {format:?} to a file."),
+        };
 
         use image::{ImageBuffer, Rgba};
         let Some(buffer) =
-            ImageBuffer::<Rgba<u8>, _>::from_raw(texture.width(), texture.height(), data)
+            ImageBuffer::<Rgba<u8>, _>::from_raw(texture.width(), texture.height(), pixels)
         else {
             bail!("Couldn't save image to file.")
         };
@@ -140,3 +213,12 @@ pub fn write_to_file<SurfaceManager>(
 
     Ok(())
 }
+
+/// Converts an accumulated texel (sum of samples in rgb, sample count in alpha) to a gamma
+/// encoded 8-bit color.
+fn resolve_accumulated([r, g, b, samples]: [f32; 4]) -> [u8; 4] {
+    let samples = samples.max(1.);
+    // sqrt: convert to gamma space
+    let encode = |c: f32| ((c / samples).sqrt().clamp(0., 1.) * 255.).round() as u8;
+    [encode(r), encode(g), encode(b), u8::MAX]
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use ray::objects::material;
 use winit::event_loop::EventLoop;
 
@@ -7,12 +9,12 @@ fn main() {
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
 
+    let ground = ray::objects::Plane::new(
+        [0., -0.5, 0.],
+        [0., 1., 0.],
+        material::Material::lambertian([0.8, 0.8, 0.]),
+    );
     let spheres = vec![
-        ray::objects::Sphere::new(
-            [0., -100.5, -1.0],
-            100.,
-            material::Material::lambertian([0.8, 0.8, 0.]),
-        ),
         ray::objects::Sphere::new(
             [0., 0., -1.2],
             0.5,
@@ -30,7 +32,32 @@ fn main() {
             material::Material::metal([0.8, 0.6, 0.2], 1.0),
         ),
     ];
-    let mut app = ray::App::new(spheres);
+    let camera = ray::Camera::new([-2., 2., 1.], [0., 0., -1.], [0., 1., 0.], 20.);
+    // Meshes can be loaded from an OBJ, glTF or GLB file given as the first argument, along
+    // with the first camera of glTF files.
+    let (scene, camera) = match std::env::args_os().nth(1).map(PathBuf::from) {
+        Some(path)
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "gltf" || extension == "glb") =>
+        {
+            let (scene, gltf_camera) = ray::objects::load_gltf(&path).unwrap();
+            (
+                ray::objects::Scene { spheres, ..scene },
+                gltf_camera.unwrap_or(camera),
+            )
+        }
+        Some(path) => (
+            ray::objects::Scene {
+                spheres,
+                ..ray::objects::load_obj(&path).unwrap()
+            },
+            camera,
+        ),
+        None => (ray::objects::Scene::new(spheres), camera),
+    };
+    let scene = scene.with_planes([ground]);
+    let mut app = ray::App::new(scene, camera, ray::RenderSettings::default());
 
     event_loop.run_app(&mut app).unwrap();
 }
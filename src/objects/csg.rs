@@ -0,0 +1,138 @@
+use super::{
+    Cone, Cylinder, Material, Sphere, Torus,
+    shape::{self, Shape},
+};
+use crate::bvh::Aabb;
+
+const MAX_PRIMITIVES: usize = 32;
+
+/// A solid made by combining closed primitives with constructive solid geometry. Every
+/// primitive keeps its material, so the surfaces made by cutting a solid have the material of
+/// the primitive that cut it.
+///
+/// ```
+/// use ray::objects::{Csg, Sphere, material::Material};
+///
+/// // A biconvex lens.
+/// let glass = Material::dieletric(1.5);
+/// let lens = Csg::from(Sphere::new([0., 0., 0.8], 1., glass))
+///     .intersection(Sphere::new([0., 0., -0.8], 1., glass));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Csg {
+    // Primitives and operations in postfix order, the root being the last node.
+    nodes: Vec<Shape>,
+}
+
+impl Csg {
+    /// Axis aligned box with opposite corners `a` and `b`.
+    #[must_use]
+    pub fn cuboid(a: [f32; 3], b: [f32; 3], material: Material) -> Self {
+        Self {
+            nodes: vec![Shape::cuboid(a, b, material)],
+        }
+    }
+
+    /// The solid inside either of the solids.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the result has more than 32 primitives.
+    #[must_use]
+    pub fn union(self, other: impl Into<Self>) -> Self {
+        self.combine(other.into(), shape::UNION)
+    }
+
+    /// The solid inside both solids.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the result has more than 32 primitives.
+    #[must_use]
+    pub fn intersection(self, other: impl Into<Self>) -> Self {
+        self.combine(other.into(), shape::INTERSECTION)
+    }
+
+    /// The solid inside this one but outside of `other`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the result has more than 32 primitives.
+    #[must_use]
+    pub fn difference(self, other: impl Into<Self>) -> Self {
+        self.combine(other.into(), shape::DIFFERENCE)
+    }
+
+    fn combine(mut self, other: Self, operation: u32) -> Self {
+        self.nodes.extend(other.nodes);
+        self.nodes.push(Shape::operation(operation));
+        // The shader tracks whether the ray is inside each primitive with the bits of an u32.
+        assert!(
+            self.primitives() <= MAX_PRIMITIVES,
+            "CSG solids can't have more than {MAX_PRIMITIVES} primitives."
+        );
+        self
+    }
+
+    fn primitives(&self) -> usize {
+        self.nodes.len().div_ceil(2)
+    }
+
+    pub(crate) fn nodes(&self) -> &[Shape] {
+        &self.nodes
+    }
+
+    pub(crate) fn aabb(&self) -> Aabb {
+        let mut stack = Vec::new();
+        for node in &self.nodes {
+            let aabb = match node.kind() {
+                shape::UNION | shape::INTERSECTION | shape::DIFFERENCE => {
+                    let (b, a) = (stack.pop(), stack.pop());
+                    let (Some(a), Some(b)) = (a, b) else {
+                        unreachable!("Operations always follow two solids.")
+                    };
+                    match node.kind() {
+                        shape::UNION => Aabb::union(a, b),
+                        shape::INTERSECTION => a.intersection(b),
+                        _ => a,
+                    }
+                }
+                _ => node.aabb(),
+            };
+            stack.push(aabb);
+        }
+        stack.pop().unwrap_or(Aabb::EMPTY)
+    }
+}
+
+impl From<Sphere> for Csg {
+    fn from(sphere: Sphere) -> Self {
+        Self {
+            nodes: vec![sphere.shape()],
+        }
+    }
+}
+
+impl From<Cylinder> for Csg {
+    fn from(cylinder: Cylinder) -> Self {
+        Self {
+            nodes: vec![cylinder.shape()],
+        }
+    }
+}
+
+impl From<Cone> for Csg {
+    fn from(cone: Cone) -> Self {
+        Self {
+            nodes: vec![cone.shape()],
+        }
+    }
+}
+
+impl From<Torus> for Csg {
+    fn from(torus: Torus) -> Self {
+        Self {
+            nodes: vec![torus.shape()],
+        }
+    }
+}
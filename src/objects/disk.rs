@@ -0,0 +1,58 @@
+use super::Material;
+use crate::bvh::Aabb;
+
+/// A flat disk facing `normal`, whose front face is the one the normal points to.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Disk {
+    center: [f32; 3],
+    // Infinite for planes.
+    radius: f32,
+    normal: [f32; 3],
+    normal_padding: u32,
+    material: Material,
+}
+
+impl Disk {
+    #[must_use]
+    pub const fn new(center: [f32; 3], normal: [f32; 3], radius: f32, material: Material) -> Self {
+        Self {
+            center,
+            radius,
+            normal,
+            normal_padding: 0,
+            material,
+        }
+    }
+
+    pub(crate) fn aabb(&self) -> Aabb {
+        Aabb::disk(self.center.into(), self.normal.into(), self.radius)
+    }
+}
+
+/// An infinite plane through `point`, whose front face is the one `normal` points to.
+///
+/// Planes have no bounds, so they can't be part of geometries and are tested against every
+/// ray.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+    pub point: [f32; 3],
+    pub normal: [f32; 3],
+    pub material: Material,
+}
+
+impl Plane {
+    #[must_use]
+    pub const fn new(point: [f32; 3], normal: [f32; 3], material: Material) -> Self {
+        Self {
+            point,
+            normal,
+            material,
+        }
+    }
+
+    /// The disk of infinite radius the shader draws the plane as.
+    pub(crate) const fn disk(&self) -> Disk {
+        Disk::new(self.point, self.normal, f32::INFINITY, self.material)
+    }
+}
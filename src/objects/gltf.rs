@@ -0,0 +1,240 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use glam::{Mat4, Vec3};
+use gltf::{Document, Gltf, buffer, image::Format, mesh::Mode};
+use image::{DynamicImage, RgbImage, RgbaImage};
+
+use super::{Geometry, Instance, Material, Mesh, Scene, Texture};
+use crate::{Camera, Projection};
+
+// Extensions changing how materials are converted, every other one is ignored.
+const SUPPORTED_EXTENSIONS: [&str; 3] = [
+    "KHR_materials_emissive_strength",
+    "KHR_materials_ior",
+    "KHR_materials_transmission",
+];
+
+/// Loads the meshes and the first camera of the default scene of a glTF or GLB file.
+///
+/// Every glTF mesh becomes a geometry, drawn by an instance for each node using it, with a mesh
+/// per primitive. Metallic-roughness materials are converted to the closest material kind,
+/// keeping only their base color texture. Features that can't be rendered, like unsupported
+/// extensions or primitives that aren't triangles, are skipped with a warning.
+///
+/// # Errors
+///
+/// Returns an error if the file or the buffers it references can't be read or are malformed.
+pub fn load_gltf(path: &Path) -> Result<(Scene, Option<Camera>)> {
+    let bytes = fs::read(path).with_context(|| format!("Couldn't read {}", path.display()))?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    let result = parse_gltf(&bytes, directory);
+    result.with_context(|| format!("Couldn't load {}", path.display()))
+}
+
+fn parse_gltf(bytes: &[u8], directory: &Path) -> Result<(Scene, Option<Camera>)> {
+    let Gltf { document, blob } = Gltf::from_slice_without_validation(bytes)?;
+
+    // Unsupported extensions are only validated when they're required, which doesn't prevent
+    // rendering what can be rendered.
+    let mut json = document.into_json();
+    for extension in &json.extensions_used {
+        if !SUPPORTED_EXTENSIONS.contains(&extension.as_str()) {
+            log::warn!("Ignoring the unsupported glTF extension {extension}.");
+        }
+    }
+    json.extensions_required
+        .retain(|extension| SUPPORTED_EXTENSIONS.contains(&extension.as_str()));
+    let document = Document::from_json(json)?;
+    let buffers = gltf::import_buffers(&document, Some(directory), blob)?;
+
+    let mut scene = Scene::default();
+    // Index in the scene's images of each glTF image, if a base color texture uses it.
+    let mut images = vec![None; document.images().len()];
+    for material in document.materials() {
+        let Some(info) = material.pbr_metallic_roughness().base_color_texture() else {
+            continue;
+        };
+        let image = info.texture().source();
+        if info.tex_coord() == 0 && images[image.index()].is_none() {
+            let data = gltf::image::Data::from_source(image.source(), Some(directory), &buffers)?;
+            images[image.index()] = Some(scene.add_image(load_image(data, image.index())));
+        }
+    }
+    // Index in the scene's geometries of each glTF mesh, if it has triangles.
+    let geometries = document
+        .meshes()
+        .map(|mesh| {
+            let geometry = Geometry {
+                meshes: load_mesh(&mesh, &buffers, &images)?,
+                ..Default::default()
+            };
+            Ok((!geometry.meshes.is_empty()).then(|| scene.add_geometry(geometry)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut camera = None;
+    let mut nodes: Vec<_> = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .into_iter()
+        .flat_map(|scene| scene.nodes())
+        .map(|node| (node, Mat4::IDENTITY))
+        .collect();
+    // Depth first, so the first camera is the one found first when reading the file.
+    nodes.reverse();
+    while let Some((node, parent_transform)) = nodes.pop() {
+        let transform = parent_transform * Mat4::from_cols_array_2d(&node.transform().matrix());
+
+        if let Some(geometry) = node.mesh().and_then(|mesh| geometries[mesh.index()]) {
+            scene.instances.push(Instance::new(geometry, transform));
+        }
+        if camera.is_none() {
+            camera = node.camera().map(|camera| load_camera(&camera, transform));
+        }
+        let children: Vec<_> = node.children().collect();
+        nodes.extend(children.into_iter().rev().map(|child| (child, transform)));
+    }
+
+    Ok((scene, camera))
+}
+
+/// Loads the triangle primitives of a glTF mesh, given the scene's index of each glTF image.
+fn load_mesh(
+    mesh: &gltf::Mesh,
+    buffers: &[buffer::Data],
+    images: &[Option<usize>],
+) -> Result<Vec<Mesh>> {
+    let name = mesh.name().unwrap_or("unnamed");
+    let mut meshes = Vec::new();
+
+    for primitive in mesh.primitives() {
+        if primitive.mode() != Mode::Triangles {
+            log::warn!(
+                "Skipping a primitive of the mesh {name} drawn as {:?} instead of triangles.",
+                primitive.mode()
+            );
+            continue;
+        }
+
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        let Some(positions) = reader.read_positions() else {
+            log::warn!("Skipping a primitive of the mesh {name} without positions.");
+            continue;
+        };
+        let positions: Vec<_> = positions.collect();
+        let indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..u32::try_from(positions.len()).context("Too many vertices.")?).collect(),
+        };
+        let indices = indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect();
+
+        let material = load_material(&primitive.material(), images);
+        let mesh = match reader.read_normals() {
+            Some(normals) => {
+                Mesh::with_normals(&positions, &normals.collect::<Vec<_>>(), indices, material)
+            }
+            None => Mesh::new(&positions, indices, material),
+        };
+        meshes.push(match reader.read_tex_coords(0) {
+            // glTF's v goes down the textures.
+            Some(uvs) => {
+                mesh.with_uvs(&uvs.into_f32().map(|[u, v]| [u, 1. - v]).collect::<Vec<_>>())
+            }
+            None => mesh,
+        });
+    }
+    Ok(meshes)
+}
+
+/// Emitting materials become lights, transmissive materials become dielectrics and everything
+/// else is principled.
+fn load_material(material: &gltf::Material, images: &[Option<usize>]) -> Material {
+    let name = material.name().unwrap_or("unnamed");
+    let pbr = material.pbr_metallic_roughness();
+    if pbr.metallic_roughness_texture().is_some() || material.normal_texture().is_some() {
+        log::warn!("Ignoring the metallic-roughness and normal textures of the material {name}.");
+    }
+    let texture = pbr.base_color_texture().and_then(|info| {
+        if info.tex_coord() != 0 {
+            log::warn!("Ignoring the base color texture of the material {name} on another UV set.");
+            return None;
+        }
+        images[info.texture().source().index()].map(Texture::Image)
+    });
+
+    let emission = material.emissive_factor();
+    let [r, g, b, _] = pbr.base_color_factor();
+    let transmission = material
+        .transmission()
+        .map_or(0., |transmission| transmission.transmission_factor());
+    let converted = if emission != [0.; 3] {
+        Material::emissive(emission, material.emissive_strength().unwrap_or(1.))
+    } else if transmission >= 0.5 {
+        Material::dieletric(material.ior().unwrap_or(1.5))
+    } else {
+        // The specular reflecting as much light as the refractive index at normal incidence.
+        let ior = material.ior().unwrap_or(1.5);
+        let specular = ((ior - 1.) / (ior + 1.)).powi(2) / 0.08;
+        Material::principled(
+            [r, g, b],
+            pbr.metallic_factor(),
+            pbr.roughness_factor(),
+            specular,
+        )
+    };
+    match texture {
+        Some(texture) => converted.with_texture(texture),
+        None => converted,
+    }
+}
+
+/// Converts images with 8 bits per channel, replacing the others with a white pixel.
+fn load_image(image: gltf::image::Data, index: usize) -> RgbaImage {
+    let converted = match image.format {
+        Format::R8G8B8A8 => RgbaImage::from_raw(image.width, image.height, image.pixels),
+        Format::R8G8B8 => RgbImage::from_raw(image.width, image.height, image.pixels)
+            .map(|image| DynamicImage::ImageRgb8(image).into_rgba8()),
+        _ => None,
+    };
+    converted.unwrap_or_else(|| {
+        log::warn!(
+            "Ignoring the image {index}, whose {:?} format isn't supported.",
+            image.format
+        );
+        RgbaImage::from_pixel(1, 1, image::Rgba([255; 4]))
+    })
+}
+
+/// A camera at the node's origin looking down its -Z axis, with +Y up.
+fn load_camera(camera: &gltf::Camera, transform: Mat4) -> Camera {
+    let look_from = transform.transform_point3(Vec3::ZERO);
+    let look_at = transform.transform_point3(Vec3::NEG_Z);
+    let up = transform.transform_vector3(Vec3::Y);
+
+    match camera.projection() {
+        gltf::camera::Projection::Perspective(perspective) => {
+            let camera = Camera::new(
+                look_from.into(),
+                look_at.into(),
+                up.into(),
+                perspective.yfov().to_degrees(),
+            );
+            match perspective.aspect_ratio() {
+                Some(aspect_ratio) => camera.with_aspect_ratio(aspect_ratio),
+                None => camera,
+            }
+        }
+        gltf::camera::Projection::Orthographic(orthographic) => {
+            Camera::new(look_from.into(), look_at.into(), up.into(), 90.)
+                .with_aspect_ratio(orthographic.xmag() / orthographic.ymag())
+                .with_projection(Projection::Orthographic {
+                    height: 2. * orthographic.ymag(),
+                })
+        }
+    }
+}
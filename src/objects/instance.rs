@@ -0,0 +1,55 @@
+use glam::Mat4;
+
+use super::{Cone, Csg, Cylinder, Disk, Mesh, Quad, Sdf, Sphere, Torus};
+
+/// Objects in their own coordinate space, which are only rendered through the instances
+/// referencing them.
+#[derive(Clone, Debug, Default)]
+pub struct Geometry {
+    pub spheres: Vec<Sphere>,
+    pub meshes: Vec<Mesh>,
+    pub quads: Vec<Quad>,
+    pub disks: Vec<Disk>,
+    pub cylinders: Vec<Cylinder>,
+    pub cones: Vec<Cone>,
+    pub tori: Vec<Torus>,
+    pub csg: Vec<Csg>,
+    pub sdfs: Vec<Sdf>,
+}
+
+/// A copy of one of the scene's geometries, placed in the world by an affine transform.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Instance {
+    /// Index of the geometry in the scene's geometries.
+    pub geometry: usize,
+    /// Transform from the geometry's space to the world, which must be invertible.
+    pub transform: Mat4,
+}
+
+impl Instance {
+    #[must_use]
+    pub const fn new(geometry: usize, transform: Mat4) -> Self {
+        Self {
+            geometry,
+            transform,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct InstanceUniform {
+    pub(crate) world_to_object: [[f32; 4]; 4],
+    pub(crate) object_to_world: [[f32; 4]; 4],
+    pub(crate) bvh_root: u32,
+    pub(crate) bvh_root_padding: u32,
+    // Ranges of the geometry's objects in the buffers, from the first to one past the last.
+    pub(crate) spheres: [u32; 2],
+    pub(crate) triangles: [u32; 2],
+    pub(crate) quads: [u32; 2],
+    pub(crate) disks: [u32; 2],
+    // Only the world's objects have planes.
+    pub(crate) planes: [u32; 2],
+    pub(crate) shapes: [u32; 2],
+    pub(crate) shapes_padding: [u32; 2],
+}
@@ -1,23 +1,56 @@
+use super::Texture;
+
 pub const LAMBERTIAN: u32 = 0;
 pub const METAL: u32 = 1;
 pub const DIELETRIC: u32 = 2;
+pub const MEDIUM: u32 = 3;
+pub const EMISSIVE: u32 = 4;
+pub const PRINCIPLED: u32 = 5;
+
+// What changes the albedo across surfaces.
+const SOLID_COLOR: u32 = 0;
+const IMAGE_TEXTURE: u32 = 1;
+const CHECKER_TEXTURE: u32 = 2;
+const TURBULENCE_TEXTURE: u32 = 3;
+const MARBLE_TEXTURE: u32 = 4;
+const WOOD_TEXTURE: u32 = 5;
+const NOISE_TEXTURE: u32 = 6;
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Material {
     ty: u32,
+    // Fuzziness of metals, or roughness of principled materials.
     fuzziness: f32,
+    // Refractive index of dielectrics, or of the specular layer of principled materials.
     refractive_index: f32,
-    padding: f32,
+    density: f32,
     albedo: [f32; 3],
+    anisotropy: f32,
+    // Radiance emitted by emissive materials, relative to their albedo.
+    intensity: f32,
+    texture: u32,
+    // Layer of the image texture.
+    image: u32,
+    // Size of the procedural texture's features, and the color its pattern blends into.
+    scale: f32,
+    color: [f32; 3],
+    metallic: f32,
 }
 
 const ZERO_MATERIAL: Material = Material {
     ty: 0,
     fuzziness: 0.,
     refractive_index: 0.,
-    padding: 0.,
+    density: 0.,
     albedo: [0.; 3],
+    anisotropy: 0.,
+    intensity: 0.,
+    texture: SOLID_COLOR,
+    image: 0,
+    scale: 0.,
+    color: [0.; 3],
+    metallic: 0.,
 };
 
 impl Material {
@@ -48,4 +81,79 @@ impl Material {
             ..ZERO_MATERIAL
         }
     }
+
+    /// A homogeneous participating medium, like fog or smoke, filling the closed shape it's
+    /// applied to. `density` is the probability of scattering per unit of distance, and
+    /// `anisotropy` the asymmetry of the Henyey-Greenstein phase function, from -1 (back
+    /// scattering) through 0 (isotropic) to 1 (forward scattering).
+    #[must_use]
+    pub const fn medium(albedo: [f32; 3], density: f32, anisotropy: f32) -> Self {
+        Self {
+            ty: MEDIUM,
+            density,
+            albedo,
+            anisotropy,
+            ..ZERO_MATERIAL
+        }
+    }
+
+    /// A light emitting `color` scaled by `intensity` from its front face, without reflecting
+    /// any light.
+    #[must_use]
+    pub const fn emissive(color: [f32; 3], intensity: f32) -> Self {
+        Self {
+            ty: EMISSIVE,
+            albedo: color,
+            intensity,
+            ..ZERO_MATERIAL
+        }
+    }
+
+    /// A metallic-roughness material, with the parameters of glTF and of Blender's Principled
+    /// BSDF. Metals, whose `metallic` is 1, reflect `base_color` off microfacets. Other
+    /// materials are diffuse under a white specular layer reflecting `0.08 * specular` of the
+    /// light at normal incidence, so the default `specular` of 0.5 is 4%, like most
+    /// dielectrics. `roughness` goes from 0 for mirrors to 1 for the most scattering surfaces.
+    #[must_use]
+    pub fn principled(base_color: [f32; 3], metallic: f32, roughness: f32, specular: f32) -> Self {
+        // The refractive index whose Fresnel reflectance at normal incidence is the specular's.
+        let reflectance = (0.08 * specular.max(0.)).sqrt().min(0.99);
+        Self {
+            ty: PRINCIPLED,
+            fuzziness: roughness,
+            refractive_index: (1. + reflectance) / (1. - reflectance),
+            albedo: base_color,
+            metallic,
+            ..ZERO_MATERIAL
+        }
+    }
+
+    /// Varies the albedo across the surface with a texture.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a procedural texture's size isn't positive.
+    #[must_use]
+    pub const fn with_texture(mut self, texture: Texture) -> Self {
+        let (kind, scale, color) = match texture {
+            Texture::Image(image) => {
+                self.image = image as u32;
+                (IMAGE_TEXTURE, 0., [0.; 3])
+            }
+            Texture::Checker { size, color } => (CHECKER_TEXTURE, size, color),
+            Texture::Noise { size, color } => (NOISE_TEXTURE, size, color),
+            Texture::Turbulence { size, color } => (TURBULENCE_TEXTURE, size, color),
+            Texture::Marble { size, color } => (MARBLE_TEXTURE, size, color),
+            Texture::Wood { size, color } => (WOOD_TEXTURE, size, color),
+        };
+        // Points are divided by the size, which must also not be NaN.
+        assert!(
+            kind == IMAGE_TEXTURE || scale > 0.,
+            "Procedural textures must have a positive size."
+        );
+        self.texture = kind;
+        self.scale = scale;
+        self.color = color;
+        self
+    }
 }
@@ -0,0 +1,156 @@
+use glam::Vec3;
+
+use super::Material;
+use crate::bvh::Aabb;
+
+/// A triangle mesh with a single material.
+#[derive(Clone, Debug)]
+pub struct Mesh {
+    vertices: Vec<Vertex>,
+    indices: Vec<[u32; 3]>,
+    material: Material,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct Vertex {
+    position: [f32; 3],
+    // Texture coordinates, stored in the padding of the vectors.
+    u: f32,
+    // Shading normal, a zero normal means the triangle's own normal is used.
+    normal: [f32; 3],
+    v: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct Triangle {
+    vertices: [u32; 3],
+    vertices_padding: u32,
+    material: Material,
+}
+
+impl Mesh {
+    /// A mesh with flat shading, where `indices` holds the positions of each triangle's
+    /// vertices in counter-clockwise order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an index is out of bounds of `positions`.
+    #[must_use]
+    pub fn new(positions: &[[f32; 3]], indices: Vec<[u32; 3]>, material: Material) -> Self {
+        Self::with_normals(
+            positions,
+            &vec![[0.; 3]; positions.len()],
+            indices,
+            material,
+        )
+    }
+
+    /// A mesh whose shading normals are interpolated from the `normals` of its vertices.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `normals` and `positions` have different lengths or if an index is out of
+    /// bounds of `positions`.
+    #[must_use]
+    pub fn with_normals(
+        positions: &[[f32; 3]],
+        normals: &[[f32; 3]],
+        indices: Vec<[u32; 3]>,
+        material: Material,
+    ) -> Self {
+        assert_eq!(
+            positions.len(),
+            normals.len(),
+            "Every vertex of the mesh needs a normal."
+        );
+        assert!(
+            indices
+                .iter()
+                .flatten()
+                .all(|&i| (i as usize) < positions.len()),
+            "Mesh indices must refer to one of its {} vertices.",
+            positions.len()
+        );
+
+        let vertices = positions
+            .iter()
+            .zip(normals)
+            .map(|(&position, &normal)| Vertex {
+                position,
+                normal,
+                ..Default::default()
+            })
+            .collect();
+
+        Self {
+            vertices,
+            indices,
+            material,
+        }
+    }
+
+    /// Replaces the shading normals with the area weighted average of the normals of the
+    /// triangles around each vertex.
+    #[must_use]
+    pub fn with_smooth_normals(mut self) -> Self {
+        let mut normals = vec![Vec3::ZERO; self.vertices.len()];
+        for triangle in &self.indices {
+            let [a, b, c] = triangle.map(|i| Vec3::from(self.vertices[i as usize].position));
+            // The cross product's length is twice the triangle's area.
+            let normal = (b - a).cross(c - a);
+            for &i in triangle {
+                normals[i as usize] += normal;
+            }
+        }
+
+        for (vertex, normal) in self.vertices.iter_mut().zip(normals) {
+            vertex.normal = normal.normalize_or_zero().into();
+        }
+        self
+    }
+
+    /// Sets the texture coordinates of the vertices, whose `v` goes up the textures.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `uvs` doesn't have a coordinate for each vertex.
+    #[must_use]
+    pub fn with_uvs(mut self, uvs: &[[f32; 2]]) -> Self {
+        assert_eq!(
+            self.vertices.len(),
+            uvs.len(),
+            "Every vertex of the mesh needs texture coordinates."
+        );
+        for (vertex, &[u, v]) in self.vertices.iter_mut().zip(uvs) {
+            vertex.u = u;
+            vertex.v = v;
+        }
+        self
+    }
+
+    #[must_use]
+    pub const fn material(&self) -> &Material {
+        &self.material
+    }
+
+    pub(crate) fn triangle_aabbs(&self) -> impl Iterator<Item = Aabb> + '_ {
+        self.indices.iter().map(|triangle| {
+            Aabb::from_points(triangle.map(|i| Vec3::from(self.vertices[i as usize].position)))
+        })
+    }
+
+    pub(crate) fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+
+    /// The mesh's triangles, with indices offset by `first_vertex`.
+    pub(crate) fn triangles(&self, first_vertex: u32) -> impl Iterator<Item = Triangle> + '_ {
+        self.indices.iter().map(move |indices| Triangle {
+            vertices: indices.map(|i| first_vertex + i),
+            vertices_padding: 0,
+            material: self.material,
+        })
+    }
+}
@@ -1,5 +1,27 @@
+mod csg;
+mod disk;
+mod gltf;
+mod instance;
 pub mod material;
+mod mesh;
+mod obj;
+mod quad;
+mod scene;
+mod sdf;
+mod shape;
 mod sphere;
+mod texture;
 
+pub use self::gltf::load_gltf;
+pub use csg::Csg;
+pub use disk::{Disk, Plane};
+pub use instance::{Geometry, Instance};
 pub use material::Material;
+pub use mesh::Mesh;
+pub use obj::load_obj;
+pub use quad::Quad;
+pub use scene::Scene;
+pub use sdf::Sdf;
+pub use shape::{Cone, Cylinder, Torus};
 pub use sphere::Sphere;
+pub use texture::Texture;
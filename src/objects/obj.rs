@@ -0,0 +1,309 @@
+use std::{collections::HashMap, fs, path::Path, str::SplitWhitespace};
+
+use anyhow::{Context, Result, bail};
+
+use super::{Material, Mesh, Scene};
+
+const DEFAULT_ALBEDO: [f32; 3] = [0.8; 3];
+const DEFAULT_MATERIAL: Material = Material::lambertian(DEFAULT_ALBEDO);
+
+/// Loads the meshes of a Wavefront OBJ file, one per material, with the materials of the
+/// MTL libraries it references.
+///
+/// # Errors
+///
+/// Returns an error if the OBJ or MTL files can't be read or are malformed.
+pub fn load_obj(path: &Path) -> Result<Scene> {
+    let source =
+        fs::read_to_string(path).with_context(|| format!("Couldn't read {}", path.display()))?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    parse_obj(&source, path, |library| {
+        let path = directory.join(library);
+        let source = fs::read_to_string(&path)
+            .with_context(|| format!("Couldn't read {}", path.display()))?;
+        parse_mtl(&source, &path)
+    })
+}
+
+/// Parses an OBJ file named `name`, loading MTL libraries with `load_library`.
+fn parse_obj(
+    source: &str,
+    name: &Path,
+    mut load_library: impl FnMut(&str) -> Result<HashMap<String, Material>>,
+) -> Result<Scene> {
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut materials = HashMap::new();
+    let mut meshes: Vec<MeshBuilder> = Vec::new();
+    let mut current = None;
+
+    for (number, line) in source.lines().enumerate() {
+        let mut arguments = line.split_whitespace();
+        let result = match arguments.next() {
+            Some("v") => parse_floats(&mut arguments).map(|position| positions.push(position)),
+            Some("vt") => parse_uv(&mut arguments).map(|uv| uvs.push(uv)),
+            Some("vn") => parse_floats(&mut arguments).map(|normal| normals.push(normal)),
+            Some("f") => {
+                let mesh = *current.get_or_insert_with(|| {
+                    meshes.push(MeshBuilder::new(None, DEFAULT_MATERIAL));
+                    meshes.len() - 1
+                });
+                meshes[mesh].add_face(arguments, &positions, &uvs, &normals)
+            }
+            Some("usemtl") => rest(line, "usemtl").and_then(|name| {
+                let Some(&material) = materials.get(name) else {
+                    bail!("Unknown material {name:?}.")
+                };
+                current = Some(
+                    meshes
+                        .iter()
+                        .position(|mesh| mesh.name.as_deref() == Some(name))
+                        .unwrap_or_else(|| {
+                            meshes.push(MeshBuilder::new(Some(name.to_owned()), material));
+                            meshes.len() - 1
+                        }),
+                );
+                Ok(())
+            }),
+            // Unlike material names, library file names are separated by spaces.
+            Some("mtllib") => rest(line, "mtllib").and_then(|libraries| {
+                libraries.split_whitespace().try_for_each(|library| {
+                    load_library(library).map(|library| materials.extend(library))
+                })
+            }),
+            // Groups, smoothing groups, lines, comments...
+            _ => Ok(()),
+        };
+        result.with_context(|| format!("{}:{}", name.display(), number + 1))?;
+    }
+
+    Ok(Scene {
+        meshes: meshes.into_iter().map(MeshBuilder::build).collect(),
+        ..Default::default()
+    })
+}
+
+/// Parses the materials of an MTL file named `name`.
+fn parse_mtl(source: &str, name: &Path) -> Result<HashMap<String, Material>> {
+    let mut materials = Vec::<(String, MtlMaterial)>::new();
+
+    for (number, line) in source.lines().enumerate() {
+        let mut arguments = line.split_whitespace();
+        let result = match (arguments.next(), materials.last_mut()) {
+            (Some("newmtl"), _) => rest(line, "newmtl")
+                .map(|name| materials.push((name.to_owned(), MtlMaterial::default()))),
+            (Some(keyword), Some((_, material))) => material.set(keyword, &mut arguments),
+            _ => Ok(()),
+        };
+        result.with_context(|| format!("{}:{}", name.display(), number + 1))?;
+    }
+
+    Ok(materials
+        .into_iter()
+        .map(|(name, material)| (name, material.material()))
+        .collect())
+}
+
+struct MeshBuilder {
+    name: Option<String>,
+    material: Material,
+    // Index of the vertex made from each combination of position, texture coordinates and
+    // normal.
+    vertex_indices: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+    positions: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    normals: Vec<[f32; 3]>,
+    indices: Vec<[u32; 3]>,
+}
+
+impl MeshBuilder {
+    fn new(name: Option<String>, material: Material) -> Self {
+        Self {
+            name,
+            material,
+            vertex_indices: HashMap::new(),
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    /// Adds a polygon, split in a fan of triangles.
+    fn add_face(
+        &mut self,
+        corners: SplitWhitespace,
+        positions: &[[f32; 3]],
+        uvs: &[[f32; 2]],
+        normals: &[[f32; 3]],
+    ) -> Result<()> {
+        let corners = corners
+            .map(|corner| self.add_vertex(corner, positions, uvs, normals))
+            .collect::<Result<Vec<_>>>()?;
+        if corners.len() < 3 {
+            bail!("Faces need at least 3 vertices, found {}.", corners.len());
+        }
+
+        self.indices.extend(
+            corners
+                .windows(2)
+                .skip(1)
+                .map(|pair| [corners[0], pair[0], pair[1]]),
+        );
+        Ok(())
+    }
+
+    /// Adds a face corner in the `v`, `v/vt`, `v//vn` or `v/vt/vn` format.
+    fn add_vertex(
+        &mut self,
+        corner: &str,
+        positions: &[[f32; 3]],
+        uvs: &[[f32; 2]],
+        normals: &[[f32; 3]],
+    ) -> Result<u32> {
+        let mut references = corner.split('/');
+        let position = resolve_index(references.next(), positions.len(), "vertex")?
+            .with_context(|| format!("Face corner {corner:?} has no vertex."))?;
+        let uv = resolve_index(references.next(), uvs.len(), "texture coordinate")?;
+        let normal = resolve_index(references.next(), normals.len(), "normal")?;
+
+        let next_index = u32::try_from(self.positions.len()).context("Too many vertices.")?;
+        let index = *self
+            .vertex_indices
+            .entry((position, uv, normal))
+            .or_insert(next_index);
+        if index == next_index {
+            self.positions.push(positions[position]);
+            self.uvs.push(uv.map_or([0.; 2], |uv| uvs[uv]));
+            // Corners without a normal use the triangle's own normal.
+            self.normals
+                .push(normal.map_or([0.; 3], |normal| normals[normal]));
+        }
+        Ok(index)
+    }
+
+    fn build(self) -> Mesh {
+        Mesh::with_normals(&self.positions, &self.normals, self.indices, self.material)
+            .with_uvs(&self.uvs)
+    }
+}
+
+/// Converts a 1-based, or negative and relative to the end, OBJ index to a 0-based one.
+fn resolve_index(reference: Option<&str>, len: usize, kind: &str) -> Result<Option<usize>> {
+    let Some(reference) = reference.filter(|reference| !reference.is_empty()) else {
+        return Ok(None);
+    };
+    let index: isize = reference
+        .parse()
+        .with_context(|| format!("Invalid {kind} index {reference:?}."))?;
+
+    let resolved = match index {
+        1.. => index.unsigned_abs() - 1,
+        ..0 => len.wrapping_sub(index.unsigned_abs()),
+        0 => bail!("OBJ indices start at 1."),
+    };
+    if resolved >= len {
+        bail!("The {kind} index {index} is out of bounds, only {len} are defined.");
+    }
+    Ok(Some(resolved))
+}
+
+fn parse_floats<const N: usize>(arguments: &mut SplitWhitespace) -> Result<[f32; N]> {
+    let mut values = [0.; N];
+    for value in &mut values {
+        let argument = arguments
+            .next()
+            .with_context(|| format!("Expected {N} numbers."))?;
+        *value = argument
+            .parse()
+            .with_context(|| format!("Invalid number {argument:?}."))?;
+    }
+    Ok(values)
+}
+
+/// Parses texture coordinates in the `u [v [w]]` format, where `v` defaults to 0.
+fn parse_uv(arguments: &mut SplitWhitespace) -> Result<[f32; 2]> {
+    let [u] = parse_floats(arguments)?;
+    let v = match arguments.clone().next() {
+        Some(_) => parse_floats::<1>(arguments)?[0],
+        None => 0.,
+    };
+    Ok([u, v])
+}
+
+/// The rest of the line after `keyword`, which may contain spaces.
+fn rest<'a>(line: &'a str, keyword: &str) -> Result<&'a str> {
+    let rest = line.trim().trim_start_matches(keyword).trim();
+    if rest.is_empty() {
+        bail!("{keyword} needs a name.");
+    }
+    Ok(rest)
+}
+
+struct MtlMaterial {
+    diffuse: [f32; 3],
+    specular: [f32; 3],
+    specular_exponent: f32,
+    dissolve: f32,
+    refractive_index: Option<f32>,
+    illumination: u32,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            diffuse: DEFAULT_ALBEDO,
+            specular: [0.; 3],
+            specular_exponent: 0.,
+            dissolve: 1.,
+            refractive_index: None,
+            illumination: 2,
+        }
+    }
+}
+
+impl MtlMaterial {
+    /// Sets the property of a statement, ignoring the ones that can't be rendered.
+    fn set(&mut self, keyword: &str, arguments: &mut SplitWhitespace) -> Result<()> {
+        match keyword {
+            "Kd" => self.diffuse = parse_floats(arguments)?,
+            "Ks" => self.specular = parse_floats(arguments)?,
+            "Ns" => [self.specular_exponent] = parse_floats(arguments)?,
+            "d" => [self.dissolve] = parse_floats(arguments)?,
+            "Tr" => {
+                let [transparency] = parse_floats(arguments)?;
+                self.dissolve = 1. - transparency;
+            }
+            "Ni" => {
+                let [refractive_index] = parse_floats(arguments)?;
+                self.refractive_index = Some(refractive_index);
+            }
+            "illum" => {
+                let argument = arguments.next().context("illum needs a model.")?;
+                self.illumination = argument
+                    .parse()
+                    .with_context(|| format!("Invalid illumination model {argument:?}."))?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Transparent materials become dielectrics, materials with reflections or a dominant
+    /// specular color become metals and everything else is lambertian.
+    fn material(&self) -> Material {
+        let max = |color: [f32; 3]| color.into_iter().fold(0., f32::max);
+
+        if self.dissolve < 1. || matches!(self.illumination, 4 | 6 | 7 | 9) {
+            Material::dieletric(self.refractive_index.unwrap_or(1.5))
+        } else if self.illumination == 3 || max(self.specular) > max(self.diffuse) {
+            // Roughness of the Beckmann distribution matching the Phong exponent.
+            let fuzziness = (2. / (self.specular_exponent + 2.)).sqrt();
+            Material::metal(self.specular, fuzziness)
+        } else {
+            Material::lambertian(self.diffuse)
+        }
+    }
+}
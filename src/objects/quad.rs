@@ -0,0 +1,61 @@
+use glam::Vec3;
+
+use super::Material;
+use crate::bvh::Aabb;
+
+/// A parallelogram with a corner at `origin` and sides along `u` and `v`. Its front face is
+/// the one the normal `u × v` points to.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Quad {
+    origin: [f32; 3],
+    origin_padding: u32,
+    u: [f32; 3],
+    u_padding: u32,
+    v: [f32; 3],
+    v_padding: u32,
+    material: Material,
+}
+
+impl Quad {
+    #[must_use]
+    pub const fn new(origin: [f32; 3], u: [f32; 3], v: [f32; 3], material: Material) -> Self {
+        Self {
+            origin,
+            origin_padding: 0,
+            u,
+            u_padding: 0,
+            v,
+            v_padding: 0,
+            material,
+        }
+    }
+
+    /// The six sides of the box with opposite corners `a` and `b`, facing outwards.
+    #[must_use]
+    pub fn cuboid(a: [f32; 3], b: [f32; 3], material: Material) -> [Self; 6] {
+        let (min, max) = (Vec3::from(a).min(b.into()), Vec3::from(a).max(b.into()));
+        let size = max - min;
+        let (dx, dy, dz) = (size * Vec3::X, size * Vec3::Y, size * Vec3::Z);
+
+        [
+            // Front, right, back, left, top and bottom.
+            (Vec3::new(min.x, min.y, max.z), dx, dy),
+            (Vec3::new(max.x, min.y, max.z), -dz, dy),
+            (Vec3::new(max.x, min.y, min.z), -dx, dy),
+            (min, dz, dy),
+            (Vec3::new(min.x, max.y, max.z), dx, -dz),
+            (min, dx, dz),
+        ]
+        .map(|(origin, u, v)| Self::new(origin.into(), u.into(), v.into(), material))
+    }
+
+    pub(crate) fn aabb(&self) -> Aabb {
+        let (origin, u, v) = (
+            Vec3::from(self.origin),
+            Vec3::from(self.u),
+            Vec3::from(self.v),
+        );
+        Aabb::from_points([origin, origin + u, origin + v, origin + u + v])
+    }
+}
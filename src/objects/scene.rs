@@ -0,0 +1,358 @@
+use std::{ops::Range, path::Path};
+
+use anyhow::{Context, Result};
+use glam::Mat4;
+use image::RgbaImage;
+
+use super::{
+    Cone, Csg, Cylinder, Disk, Geometry, Instance, Mesh, Plane, Quad, Sdf, Sphere, Torus,
+    instance::InstanceUniform, mesh, shape::Shape,
+};
+use crate::bvh::{self, Aabb, Primitive};
+
+/// Every object that is rendered.
+#[derive(Clone, Debug, Default)]
+pub struct Scene {
+    pub spheres: Vec<Sphere>,
+    pub meshes: Vec<Mesh>,
+    pub quads: Vec<Quad>,
+    pub disks: Vec<Disk>,
+    pub planes: Vec<Plane>,
+    pub cylinders: Vec<Cylinder>,
+    pub cones: Vec<Cone>,
+    pub tori: Vec<Torus>,
+    pub csg: Vec<Csg>,
+    pub sdfs: Vec<Sdf>,
+    /// Geometries drawn by `instances`, which may reference each of them many times.
+    pub geometries: Vec<Geometry>,
+    pub instances: Vec<Instance>,
+    /// Images referenced by textures, stretched to the size of the largest one when rendered.
+    pub images: Vec<RgbaImage>,
+}
+
+impl Scene {
+    #[must_use]
+    pub const fn new(spheres: Vec<Sphere>) -> Self {
+        Self {
+            spheres,
+            meshes: Vec::new(),
+            quads: Vec::new(),
+            disks: Vec::new(),
+            planes: Vec::new(),
+            cylinders: Vec::new(),
+            cones: Vec::new(),
+            tori: Vec::new(),
+            csg: Vec::new(),
+            sdfs: Vec::new(),
+            geometries: Vec::new(),
+            instances: Vec::new(),
+            images: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_mesh(mut self, mesh: Mesh) -> Self {
+        self.meshes.push(mesh);
+        self
+    }
+
+    #[must_use]
+    pub fn with_quads(mut self, quads: impl IntoIterator<Item = Quad>) -> Self {
+        self.quads.extend(quads);
+        self
+    }
+
+    #[must_use]
+    pub fn with_disks(mut self, disks: impl IntoIterator<Item = Disk>) -> Self {
+        self.disks.extend(disks);
+        self
+    }
+
+    #[must_use]
+    pub fn with_planes(mut self, planes: impl IntoIterator<Item = Plane>) -> Self {
+        self.planes.extend(planes);
+        self
+    }
+
+    #[must_use]
+    pub fn with_cylinders(mut self, cylinders: impl IntoIterator<Item = Cylinder>) -> Self {
+        self.cylinders.extend(cylinders);
+        self
+    }
+
+    #[must_use]
+    pub fn with_cones(mut self, cones: impl IntoIterator<Item = Cone>) -> Self {
+        self.cones.extend(cones);
+        self
+    }
+
+    #[must_use]
+    pub fn with_tori(mut self, tori: impl IntoIterator<Item = Torus>) -> Self {
+        self.tori.extend(tori);
+        self
+    }
+
+    #[must_use]
+    pub fn with_csg(mut self, csg: impl IntoIterator<Item = Csg>) -> Self {
+        self.csg.extend(csg);
+        self
+    }
+
+    #[must_use]
+    pub fn with_sdfs(mut self, sdfs: impl IntoIterator<Item = Sdf>) -> Self {
+        self.sdfs.extend(sdfs);
+        self
+    }
+
+    /// Adds a geometry that can be referenced by instances, returning its index.
+    pub fn add_geometry(&mut self, geometry: Geometry) -> usize {
+        self.geometries.push(geometry);
+        self.geometries.len() - 1
+    }
+
+    /// Adds an image that can be referenced by textures, returning its index.
+    pub fn add_image(&mut self, image: RgbaImage) -> usize {
+        self.images.push(image);
+        self.images.len() - 1
+    }
+
+    /// Loads an image that can be referenced by textures, returning its index.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or decoded.
+    pub fn load_image(&mut self, path: &Path) -> Result<usize> {
+        let image =
+            image::open(path).with_context(|| format!("Couldn't load {}", path.display()))?;
+        Ok(self.add_image(image.into_rgba8()))
+    }
+
+    #[must_use]
+    pub fn with_instances(mut self, instances: impl IntoIterator<Item = Instance>) -> Self {
+        self.instances.extend(instances);
+        self
+    }
+
+    /// Flattens the scene into the buffers read by the shader.
+    ///
+    /// The objects outside of geometries are drawn by an extra instance with the identity
+    /// transform, and they're the only ones with planes, stored before every disk. Every
+    /// geometry has its own BVH, whose leaves are its objects, and the BVH starting at the
+    /// first node has the instances as leaves. The nodes of CSG solids and distance fields come
+    /// after every other shape, outside of the geometries' ranges.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an instance references a geometry the scene doesn't have.
+    pub(crate) fn buffers(&self) -> SceneBuffers {
+        let mut buffers = SceneBuffers {
+            disks: self.planes.iter().map(Plane::disk).collect(),
+            ..Default::default()
+        };
+
+        // The world's objects are the first geometry, followed by the scene's geometries.
+        let world = Objects {
+            spheres: &self.spheres,
+            meshes: &self.meshes,
+            quads: &self.quads,
+            disks: &self.disks,
+            cylinders: &self.cylinders,
+            cones: &self.cones,
+            tori: &self.tori,
+            csg: &self.csg,
+            sdfs: &self.sdfs,
+        };
+        let mut geometries: Vec<_> = std::iter::once(world)
+            .chain(self.geometries.iter().map(|geometry| Objects {
+                spheres: &geometry.spheres,
+                meshes: &geometry.meshes,
+                quads: &geometry.quads,
+                disks: &geometry.disks,
+                cylinders: &geometry.cylinders,
+                cones: &geometry.cones,
+                tori: &geometry.tori,
+                csg: &geometry.csg,
+                sdfs: &geometry.sdfs,
+            }))
+            .map(|objects| buffers.add_geometry(&objects))
+            .collect();
+        geometries[0].planes = 0..self.planes.len();
+
+        let instances: Vec<_> = std::iter::once((0, Mat4::IDENTITY))
+            .chain(self.instances.iter().map(|instance| {
+                assert!(
+                    instance.geometry < self.geometries.len(),
+                    "An instance references geometry {}, but there are only {}.",
+                    instance.geometry,
+                    self.geometries.len()
+                );
+                (instance.geometry + 1, instance.transform)
+            }))
+            .collect();
+
+        let top_level = instances
+            .iter()
+            .enumerate()
+            .map(|(index, &(geometry, transform))| {
+                let aabb = geometries[geometry].aabb.transform(transform);
+                Primitive::new(aabb, bvh::INSTANCE, index)
+            })
+            .collect();
+        bvh::build(top_level, &mut buffers.bvh);
+
+        for geometry in &mut geometries {
+            geometry.bvh_root =
+                bvh::build(std::mem::take(&mut geometry.primitives), &mut buffers.bvh);
+        }
+
+        let offset = u32::try_from(buffers.shapes.len()).expect("Too many shapes.");
+        for shape in &mut buffers.shapes {
+            shape.offset_nodes(offset);
+        }
+        let shape_nodes = std::mem::take(&mut buffers.shape_nodes);
+        buffers.shapes.extend(shape_nodes);
+
+        buffers.instances = instances
+            .into_iter()
+            .map(|(geometry, transform)| geometries[geometry].uniform(transform))
+            .collect();
+        buffers
+    }
+}
+
+/// The scene's objects, in the layout of the shader's buffers.
+#[derive(Debug, Default)]
+pub(crate) struct SceneBuffers {
+    pub(crate) spheres: Vec<Sphere>,
+    pub(crate) vertices: Vec<mesh::Vertex>,
+    pub(crate) triangles: Vec<mesh::Triangle>,
+    pub(crate) quads: Vec<Quad>,
+    pub(crate) disks: Vec<Disk>,
+    pub(crate) shapes: Vec<Shape>,
+    // Nodes of the CSG solids and distance fields, moved after the shapes once they're all
+    // added.
+    shape_nodes: Vec<Shape>,
+    pub(crate) instances: Vec<InstanceUniform>,
+    pub(crate) bvh: Vec<bvh::Node>,
+}
+
+/// The objects of the world or of a geometry.
+struct Objects<'a> {
+    spheres: &'a [Sphere],
+    meshes: &'a [Mesh],
+    quads: &'a [Quad],
+    disks: &'a [Disk],
+    cylinders: &'a [Cylinder],
+    cones: &'a [Cone],
+    tori: &'a [Torus],
+    csg: &'a [Csg],
+    sdfs: &'a [Sdf],
+}
+
+/// Where a geometry's objects are in the buffers.
+struct GeometryRanges {
+    spheres: Range<usize>,
+    triangles: Range<usize>,
+    quads: Range<usize>,
+    disks: Range<usize>,
+    planes: Range<usize>,
+    shapes: Range<usize>,
+    primitives: Vec<Primitive>,
+    aabb: Aabb,
+    bvh_root: u32,
+}
+
+impl SceneBuffers {
+    fn add_geometry(&mut self, objects: &Objects) -> GeometryRanges {
+        let mut primitives = Vec::new();
+
+        let first_sphere = self.spheres.len();
+        for sphere in objects.spheres {
+            primitives.push(Primitive::new(
+                sphere.aabb(),
+                bvh::SPHERE,
+                self.spheres.len(),
+            ));
+            self.spheres.push(*sphere);
+        }
+
+        let first_triangle = self.triangles.len();
+        for mesh in objects.meshes {
+            let first_vertex = u32::try_from(self.vertices.len()).expect("Too many mesh vertices.");
+            for (triangle, aabb) in mesh.triangles(first_vertex).zip(mesh.triangle_aabbs()) {
+                primitives.push(Primitive::new(aabb, bvh::TRIANGLE, self.triangles.len()));
+                self.triangles.push(triangle);
+            }
+            self.vertices.extend_from_slice(mesh.vertices());
+        }
+
+        let first_quad = self.quads.len();
+        for quad in objects.quads {
+            primitives.push(Primitive::new(quad.aabb(), bvh::QUAD, self.quads.len()));
+            self.quads.push(*quad);
+        }
+
+        let first_disk = self.disks.len();
+        for disk in objects.disks {
+            primitives.push(Primitive::new(disk.aabb(), bvh::DISK, self.disks.len()));
+            self.disks.push(*disk);
+        }
+
+        let first_shape = self.shapes.len();
+        let shapes = (objects.cylinders.iter().map(Cylinder::shape))
+            .chain(objects.cones.iter().map(Cone::shape))
+            .chain(objects.tori.iter().map(Torus::shape));
+        for shape in shapes {
+            primitives.push(Primitive::new(shape.aabb(), bvh::SHAPE, self.shapes.len()));
+            self.shapes.push(shape);
+        }
+        for csg in objects.csg {
+            let first_node = self.shape_nodes.len();
+            self.shape_nodes.extend_from_slice(csg.nodes());
+            primitives.push(Primitive::new(csg.aabb(), bvh::SHAPE, self.shapes.len()));
+            self.shapes
+                .push(Shape::csg(first_node..self.shape_nodes.len()));
+        }
+        for sdf in objects.sdfs {
+            let first_node = self.shape_nodes.len();
+            self.shape_nodes.extend_from_slice(sdf.nodes());
+            primitives.push(Primitive::new(sdf.aabb(), bvh::SHAPE, self.shapes.len()));
+            self.shapes
+                .push(Shape::sdf(first_node..self.shape_nodes.len(), sdf.aabb()));
+        }
+
+        GeometryRanges {
+            spheres: first_sphere..self.spheres.len(),
+            triangles: first_triangle..self.triangles.len(),
+            quads: first_quad..self.quads.len(),
+            disks: first_disk..self.disks.len(),
+            planes: 0..0,
+            shapes: first_shape..self.shapes.len(),
+            aabb: primitives
+                .iter()
+                .fold(Aabb::EMPTY, |aabb, primitive| aabb.union(primitive.aabb)),
+            primitives,
+            bvh_root: 0,
+        }
+    }
+}
+
+impl GeometryRanges {
+    fn uniform(&self, transform: Mat4) -> InstanceUniform {
+        // Primitive::new already checked every index fits in an u32.
+        let range = |range: &Range<usize>| [range.start as u32, range.end as u32];
+        InstanceUniform {
+            world_to_object: transform.inverse().to_cols_array_2d(),
+            object_to_world: transform.to_cols_array_2d(),
+            bvh_root: self.bvh_root,
+            bvh_root_padding: 0,
+            spheres: range(&self.spheres),
+            triangles: range(&self.triangles),
+            quads: range(&self.quads),
+            disks: range(&self.disks),
+            planes: range(&self.planes),
+            shapes: range(&self.shapes),
+            shapes_padding: [0; 2],
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use glam::Vec3;
+
+use super::{Material, Torus, shape::Shape};
+use crate::bvh::Aabb;
+
+// Sizes of the shader's stacks of distances and of points before repetitions.
+const MAX_DEPTH: usize = 16;
+const MAX_REPETITIONS: usize = 4;
+
+/// A solid whose surface is where a signed distance field is zero, drawn by sphere tracing.
+/// Unlike other objects, its field can be blended and repeated. Where the field is made of
+/// several primitives, the surface has the material of the closest one.
+///
+/// ```
+/// use ray::objects::{Sdf, material::Material};
+///
+/// // A row of five blobs, each made of two spheres melting into each other.
+/// let red = Material::lambertian([0.65, 0.05, 0.05]);
+/// let blobs = Sdf::sphere([0., 0., -2.], 0.2, red)
+///     .smooth_union(Sdf::sphere([0., 0.2, -2.], 0.15, red), 0.1)
+///     .repeat([0.6, 0., 0.], [2, 0, 0]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Sdf {
+    // Primitives and operations in postfix order, the root being the last node.
+    nodes: Vec<Shape>,
+    aabb: Aabb,
+    // Distances the shader keeps while evaluating the field.
+    depth: usize,
+    // Repetitions nested in each other.
+    repetitions: usize,
+}
+
+impl Sdf {
+    #[must_use]
+    pub fn sphere(center: [f32; 3], radius: f32, material: Material) -> Self {
+        Self::primitive(Shape::sphere(center, radius, material))
+    }
+
+    /// Axis aligned box with opposite corners `a` and `b`.
+    #[must_use]
+    pub fn cuboid(a: [f32; 3], b: [f32; 3], material: Material) -> Self {
+        Self::primitive(Shape::cuboid(a, b, material))
+    }
+
+    /// A ring around `axis`, made of the points at `minor_radius` from the circle of
+    /// `major_radius` around `center`.
+    #[must_use]
+    pub fn torus(
+        center: [f32; 3],
+        axis: [f32; 3],
+        major_radius: f32,
+        minor_radius: f32,
+        material: Material,
+    ) -> Self {
+        Self::primitive(Torus::new(center, axis, major_radius, minor_radius, material).shape())
+    }
+
+    fn primitive(shape: Shape) -> Self {
+        Self {
+            nodes: vec![shape],
+            aabb: shape.aabb(),
+            depth: 1,
+            repetitions: 0,
+        }
+    }
+
+    /// The union of both fields, blended where they're closer to each other than
+    /// `smoothness`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if evaluating the result needs more than 16 distances at once, which only
+    /// happens when unions are nested in the second operand of other unions.
+    #[must_use]
+    pub fn smooth_union(mut self, other: Self, smoothness: f32) -> Self {
+        self.depth = self.depth.max(other.depth + 1);
+        assert!(
+            self.depth <= MAX_DEPTH,
+            "Distance fields can't keep more than {MAX_DEPTH} distances at once."
+        );
+        self.repetitions = self.repetitions.max(other.repetitions);
+        // The blend moves the surface up to a quarter of the smoothness away from both fields.
+        let blend = Vec3::splat(smoothness.abs() / 4.);
+        let aabb = self.aabb.union(other.aabb);
+        self.aabb = Aabb::from_points([aabb.min - blend, aabb.max + blend]);
+
+        self.nodes.extend(other.nodes);
+        self.nodes.push(Shape::smooth_union(smoothness));
+        self
+    }
+
+    /// Copies of the field every `spacing`, `limit` times on each side of it along each axis.
+    /// Copies closer than the field's size may be cut, as only the nearest one is evaluated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than 4 repetitions are nested in each other.
+    #[must_use]
+    pub fn repeat(mut self, spacing: [f32; 3], limit: [u32; 3]) -> Self {
+        self.repetitions += 1;
+        assert!(
+            self.repetitions <= MAX_REPETITIONS,
+            "Distance fields can't nest more than {MAX_REPETITIONS} repetitions."
+        );
+        let center = (self.aabb.min + self.aabb.max) / 2.;
+        let extent = (Vec3::from(spacing) * Vec3::from(limit.map(|limit| limit as f32))).abs();
+        self.aabb = Aabb::from_points([self.aabb.min - extent, self.aabb.max + extent]);
+
+        self.nodes
+            .insert(0, Shape::repeat(center.into(), spacing, limit));
+        self.nodes.push(Shape::repeat_end());
+        self
+    }
+
+    pub(crate) fn nodes(&self) -> &[Shape] {
+        &self.nodes
+    }
+
+    pub(crate) const fn aabb(&self) -> Aabb {
+        self.aabb
+    }
+}
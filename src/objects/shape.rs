@@ -0,0 +1,302 @@
+use std::ops::Range;
+
+use glam::Vec3;
+
+use super::Material;
+use crate::bvh::Aabb;
+
+// Kinds of shapes, which the shader intersects differently.
+const CONE: u32 = 0;
+const TORUS: u32 = 1;
+const SPHERE: u32 = 2;
+const CUBOID: u32 = 3;
+// Solid made with CSG, whose nodes are a range of other shapes.
+const CSG: u32 = 4;
+// Operations combining the two previous solids in a CSG solid's nodes.
+pub(crate) const UNION: u32 = 5;
+pub(crate) const INTERSECTION: u32 = 6;
+pub(crate) const DIFFERENCE: u32 = 7;
+// Signed distance field, whose nodes are a range of other shapes.
+const SDF: u32 = 8;
+// Nodes of a signed distance field besides spheres, cuboids and tori.
+const SMOOTH_UNION: u32 = 9;
+const REPEAT: u32 = 10;
+const REPEAT_END: u32 = 11;
+
+/// A cylinder from the center of its `base` to the center of its `top`, closed by flat caps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cylinder {
+    base: [f32; 3],
+    top: [f32; 3],
+    radius: f32,
+    material: Material,
+}
+
+impl Cylinder {
+    #[must_use]
+    pub const fn new(base: [f32; 3], top: [f32; 3], radius: f32, material: Material) -> Self {
+        Self {
+            base,
+            top,
+            radius,
+            material,
+        }
+    }
+
+    pub(crate) const fn shape(&self) -> Shape {
+        Shape::cone(self.base, self.radius, self.top, self.radius, self.material)
+    }
+}
+
+/// A cone closed by a flat cap at its base, or a frustum closed at both ends when its top has
+/// a radius.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cone {
+    base: [f32; 3],
+    base_radius: f32,
+    top: [f32; 3],
+    top_radius: f32,
+    material: Material,
+}
+
+impl Cone {
+    #[must_use]
+    pub const fn new(base: [f32; 3], apex: [f32; 3], radius: f32, material: Material) -> Self {
+        Self::frustum(base, radius, apex, 0., material)
+    }
+
+    /// A cone truncated at `top`, where its radius is `top_radius`.
+    #[must_use]
+    pub const fn frustum(
+        base: [f32; 3],
+        base_radius: f32,
+        top: [f32; 3],
+        top_radius: f32,
+        material: Material,
+    ) -> Self {
+        Self {
+            base,
+            base_radius,
+            top,
+            top_radius,
+            material,
+        }
+    }
+
+    pub(crate) const fn shape(&self) -> Shape {
+        Shape::cone(
+            self.base,
+            self.base_radius,
+            self.top,
+            self.top_radius,
+            self.material,
+        )
+    }
+}
+
+/// A ring around `axis`, made of the points at `minor_radius` from the circle of
+/// `major_radius` around `center`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Torus {
+    center: [f32; 3],
+    axis: [f32; 3],
+    major_radius: f32,
+    minor_radius: f32,
+    material: Material,
+}
+
+impl Torus {
+    #[must_use]
+    pub const fn new(
+        center: [f32; 3],
+        axis: [f32; 3],
+        major_radius: f32,
+        minor_radius: f32,
+        material: Material,
+    ) -> Self {
+        Self {
+            center,
+            axis,
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+
+    pub(crate) const fn shape(&self) -> Shape {
+        Shape {
+            origin: self.center,
+            radius: self.major_radius,
+            axis: self.axis,
+            second_radius: self.minor_radius,
+            kind: TORUS,
+            material: self.material,
+            ..ZERO_SHAPE
+        }
+    }
+}
+
+/// Cylinders, cones, tori, CSG solids and signed distance fields, as the shader reads them.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct Shape {
+    // Center of the base, of the torus or of the sphere, minimum corner of the cuboid or of
+    // the distance field's bounds, or repetitions on each side of a repetition.
+    origin: [f32; 3],
+    // Radius of the base, major radius of the torus, radius of the sphere, or smoothness of
+    // the smooth union.
+    radius: f32,
+    // From the base to the top, normal of the torus' plane, size of the cuboid, maximum corner
+    // of the distance field's bounds, or spacing of a repetition.
+    axis: [f32; 3],
+    // Radius of the top, or minor radius of the torus.
+    second_radius: f32,
+    kind: u32,
+    kind_padding: u32,
+    // Range of a CSG solid's or a distance field's nodes, stored in postfix order.
+    nodes: [u32; 2],
+    material: Material,
+    // Center of the field a repetition copies, around which its copies are placed.
+    center: [f32; 3],
+    center_padding: u32,
+}
+
+const ZERO_SHAPE: Shape = Shape {
+    origin: [0.; 3],
+    radius: 0.,
+    axis: [0.; 3],
+    second_radius: 0.,
+    kind: 0,
+    kind_padding: 0,
+    nodes: [0; 2],
+    material: Material::lambertian([0.; 3]),
+    center: [0.; 3],
+    center_padding: 0,
+};
+
+impl Shape {
+    const fn cone(
+        base: [f32; 3],
+        base_radius: f32,
+        top: [f32; 3],
+        top_radius: f32,
+        material: Material,
+    ) -> Self {
+        Self {
+            origin: base,
+            radius: base_radius,
+            axis: [top[0] - base[0], top[1] - base[1], top[2] - base[2]],
+            second_radius: top_radius,
+            kind: CONE,
+            material,
+            ..ZERO_SHAPE
+        }
+    }
+
+    pub(crate) const fn sphere(center: [f32; 3], radius: f32, material: Material) -> Self {
+        Self {
+            origin: center,
+            radius,
+            kind: SPHERE,
+            material,
+            ..ZERO_SHAPE
+        }
+    }
+
+    /// Axis aligned box with opposite corners `a` and `b`.
+    pub(crate) fn cuboid(a: [f32; 3], b: [f32; 3], material: Material) -> Self {
+        let (a, b) = (Vec3::from(a), Vec3::from(b));
+        Self {
+            origin: a.min(b).into(),
+            axis: (a - b).abs().into(),
+            kind: CUBOID,
+            material,
+            ..ZERO_SHAPE
+        }
+    }
+
+    pub(crate) const fn operation(kind: u32) -> Self {
+        Self { kind, ..ZERO_SHAPE }
+    }
+
+    /// A CSG solid whose nodes are `nodes` in the shapes buffer.
+    pub(crate) fn csg(nodes: Range<usize>) -> Self {
+        Self {
+            kind: CSG,
+            nodes: [nodes.start, nodes.end]
+                .map(|index| u32::try_from(index).expect("Too many shapes for CSG solids.")),
+            ..ZERO_SHAPE
+        }
+    }
+
+    /// A signed distance field within `aabb`, whose nodes are `nodes` in the shapes buffer.
+    pub(crate) fn sdf(nodes: Range<usize>, aabb: Aabb) -> Self {
+        Self {
+            origin: aabb.min.into(),
+            axis: aabb.max.into(),
+            kind: SDF,
+            nodes: [nodes.start, nodes.end]
+                .map(|index| u32::try_from(index).expect("Too many shapes for distance fields.")),
+            ..ZERO_SHAPE
+        }
+    }
+
+    /// Blends the two previous distance fields where they're closer than `smoothness`.
+    pub(crate) const fn smooth_union(smoothness: f32) -> Self {
+        Self {
+            radius: smoothness,
+            kind: SMOOTH_UNION,
+            ..ZERO_SHAPE
+        }
+    }
+
+    /// Repeats the distance field up to the next `repeat_end` node, centered on `center`, every
+    /// `spacing`, `limit` times on each side of it.
+    pub(crate) fn repeat(center: [f32; 3], spacing: [f32; 3], limit: [u32; 3]) -> Self {
+        Self {
+            origin: limit.map(|limit| limit as f32),
+            axis: spacing,
+            kind: REPEAT,
+            center,
+            ..ZERO_SHAPE
+        }
+    }
+
+    pub(crate) const fn repeat_end() -> Self {
+        Self::operation(REPEAT_END)
+    }
+
+    pub(crate) const fn kind(&self) -> u32 {
+        self.kind
+    }
+
+    /// Moves the nodes of CSG solids and distance fields `offset` shapes further in the buffer.
+    pub(crate) fn offset_nodes(&mut self, offset: u32) {
+        if matches!(self.kind, CSG | SDF) {
+            self.nodes = self.nodes.map(|index| index + offset);
+        }
+    }
+
+    pub(crate) fn aabb(&self) -> Aabb {
+        let (origin, axis) = (Vec3::from(self.origin), Vec3::from(self.axis));
+        match self.kind {
+            CONE => Aabb::disk(origin, axis, self.radius).union(Aabb::disk(
+                origin + axis,
+                axis,
+                self.second_radius,
+            )),
+            TORUS => {
+                let tube = Vec3::splat(self.second_radius.abs());
+                let ring = Aabb::disk(origin, axis, self.radius.abs());
+                Aabb::from_points([ring.min - tube, ring.max + tube])
+            }
+            SPHERE => {
+                let radius = Vec3::splat(self.radius.abs());
+                Aabb::from_points([origin - radius, origin + radius])
+            }
+            CUBOID => Aabb::from_points([origin, origin + axis]),
+            // Operations, CSG solids and distance fields are bounded by their nodes.
+            _ => Aabb::EMPTY,
+        }
+    }
+}
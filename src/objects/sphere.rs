@@ -1,4 +1,7 @@
-use super::Material;
+use glam::Vec3;
+
+use super::{Material, shape::Shape};
+use crate::bvh::Aabb;
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -6,17 +9,40 @@ pub struct Sphere {
     center: [f32; 3],
     radius: f32,
     material: Material,
-    other_padding: [u32; 1],
+    // Distance travelled by the center between times 0 and 1.
+    velocity: [f32; 3],
+    velocity_padding: [u32; 1],
 }
 
 impl Sphere {
     #[must_use]
     pub const fn new(center: [f32; 3], radius: f32, material: Material) -> Self {
+        Self::moving(center, center, radius, material)
+    }
+
+    /// A sphere whose center moves linearly from `start` at time 0 to `end` at time 1. It rests
+    /// at `start` before and at `end` after.
+    #[must_use]
+    pub const fn moving(start: [f32; 3], end: [f32; 3], radius: f32, material: Material) -> Self {
         Self {
-            center,
+            center: start,
             radius,
             material,
-            other_padding: [0; 1],
+            velocity: [end[0] - start[0], end[1] - start[1], end[2] - start[2]],
+            velocity_padding: [0; 1],
         }
     }
+
+    /// Bounds of the sphere over its whole movement.
+    pub(crate) fn aabb(&self) -> Aabb {
+        let start = Vec3::from(self.center);
+        let end = start + Vec3::from(self.velocity);
+        let radius = Vec3::splat(self.radius.abs());
+        Aabb::from_points([start - radius, start + radius, end - radius, end + radius])
+    }
+
+    /// The sphere as a CSG node, at its position at time 0.
+    pub(crate) const fn shape(&self) -> Shape {
+        Shape::sphere(self.center, self.radius, self.material)
+    }
 }
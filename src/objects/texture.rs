@@ -0,0 +1,33 @@
+/// Colors varying a material's albedo across its surface.
+///
+/// Procedural textures are solid, evaluated at the world space position of each point, so
+/// surfaces look carved from them. Their pattern blends the albedo into their `color`, with
+/// features about `size` across.
+///
+/// ```
+/// use ray::objects::{Texture, material::Material};
+///
+/// // A white and black checkerboard with squares of 0.5.
+/// let board = Material::lambertian([1.; 3]).with_texture(Texture::Checker {
+///     size: 0.5,
+///     color: [0.; 3],
+/// });
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Texture {
+    /// The image `Scene::add_image` returned the index of, scaling the albedo. It's mapped with
+    /// the surface's UV coordinates and repeated outside of the unit square.
+    Image(usize),
+    /// Cubes alternating between the albedo and the color.
+    Checker { size: f32, color: [f32; 3] },
+    /// Fractal Perlin noise, blending smoothly back and forth between the albedo and the color.
+    Noise { size: f32, color: [f32; 3] },
+    /// Fractal Perlin noise folded into creases, from the albedo in calm regions to the color
+    /// in turbulent ones.
+    Turbulence { size: f32, color: [f32; 3] },
+    /// Veins stacked along the X axis, their sides distorted by turbulence.
+    Marble { size: f32, color: [f32; 3] },
+    /// Rings around the Y axis, each growing from the albedo to the color and distorted by
+    /// turbulence.
+    Wood { size: f32, color: [f32; 3] },
+}
@@ -2,10 +2,11 @@ use std::sync::{Arc, atomic::AtomicU32};
 
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, ColorTargetState, CommandEncoder, Device, FragmentState,
-    MultisampleState, PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState,
+    BindGroupLayoutEntry, Buffer, ColorTargetState, CommandEncoder, Device, FragmentState,
+    MultisampleState, PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState, Queue,
     RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, ShaderModuleDescriptor,
     ShaderStages, TextureFormat, TextureView, TextureViewDescriptor, VertexState,
+    util::{BufferInitDescriptor, DeviceExt},
 };
 
 use crate::compute_context::ComputeContext;
@@ -15,6 +16,7 @@ pub struct RenderContext {
     pub(crate) bind_groups: [BindGroup; 2],
     pub(crate) pipeline: RenderPipeline,
     frame: Arc<AtomicU32>,
+    heatmap_uniform: Buffer,
 }
 
 impl RenderContext {
@@ -24,6 +26,12 @@ impl RenderContext {
         output_format: TextureFormat,
     ) -> Self {
         let bind_group_layout = Self::create_bind_group_layout(device);
+        let heatmap_uniform = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Heatmap Uniform"),
+            // Uniform buffers must be aligned to 16 bytes
+            contents: &0u128.to_le_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
         let bind_groups = [
             &compute_context.output_texture,
             &compute_context.previous_texture,
@@ -32,6 +40,8 @@ impl RenderContext {
             Self::create_bind_group(
                 device,
                 &texture.create_view(&TextureViewDescriptor::default()),
+                compute_context,
+                &heatmap_uniform,
                 &bind_group_layout,
             )
         });
@@ -42,9 +52,16 @@ impl RenderContext {
             pipeline,
             bind_groups,
             frame: compute_context.frame.clone(),
+            heatmap_uniform,
         }
     }
 
+    /// Displays how many samples each pixel received instead of the image. Only the display
+    /// changes, so the accumulation carries on.
+    pub fn set_heatmap(&self, queue: &Queue, heatmap: bool) {
+        queue.write_buffer(&self.heatmap_uniform, 0, &u128::from(heatmap).to_le_bytes());
+    }
+
     pub fn draw_to_texture(&self, encoder: &mut CommandEncoder, view: &TextureView) {
         let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
             label: Some("RenderPass"),
@@ -83,31 +100,82 @@ impl RenderContext {
     fn create_bind_group_layout(device: &Device) -> BindGroupLayout {
         device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: Some("Render BindGroupLayout"),
-            entries: &[BindGroupLayoutEntry {
-                binding: 0,
-                visibility: ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Texture {
-                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
-                    multisampled: false,
-                    view_dimension: wgpu::TextureViewDimension::D2,
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                // Render settings
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Frame
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Heatmap view
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+            ],
         })
     }
 
     fn create_bind_group(
         device: &Device,
         compute_texture_view: &TextureView,
+        compute_context: &ComputeContext,
+        heatmap_uniform: &Buffer,
         layout: &BindGroupLayout,
     ) -> BindGroup {
         device.create_bind_group(&BindGroupDescriptor {
             label: Some("Render BindGroup"),
             layout,
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(compute_texture_view),
-            }],
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(compute_texture_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: compute_context.render_settings_uniform.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: compute_context.frame_uniform.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: heatmap_uniform.as_entire_binding(),
+                },
+            ],
         })
     }
 
@@ -118,7 +186,13 @@ impl RenderContext {
     ) -> RenderPipeline {
         let fragment_shader = device.create_shader_module(ShaderModuleDescriptor {
             label: Some("Fragment Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/render/fragment.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(
+                concat!(
+                    include_str!("shaders/compute/settings.wgsl"),
+                    include_str!("shaders/render/fragment.wgsl")
+                )
+                .into(),
+            ),
         });
         let vertex_shader = device.create_shader_module(ShaderModuleDescriptor {
             label: Some("Vertex Shader"),
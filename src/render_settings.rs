@@ -0,0 +1,107 @@
+/// Source of the random numbers used to build each path.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Sampler {
+    /// Independent random numbers from a PCG stream per pixel.
+    #[default]
+    Pcg,
+    /// Owen scrambled Sobol points, which converge faster at low sample counts.
+    Sobol,
+}
+
+/// Stops sampling pixels once their estimated error is low enough.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdaptiveSampling {
+    /// Standard error of a pixel's mean luminance, relative to the mean, below which the pixel
+    /// stops receiving samples.
+    pub threshold: f32,
+    /// Samples a pixel receives before its error estimate is trusted.
+    pub min_samples: u32,
+}
+
+impl Default for AdaptiveSampling {
+    fn default() -> Self {
+        Self {
+            threshold: 0.01,
+            min_samples: 64,
+        }
+    }
+}
+
+/// What rays see when they leave the scene, lighting it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Background {
+    /// A gradient from white at the horizon to blue overhead.
+    #[default]
+    Sky,
+    /// A uniform color, black for scenes only lit by emissive materials.
+    Color([f32; 3]),
+}
+
+/// Sampling parameters read by the compute shader every frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderSettings {
+    /// Camera rays traced per pixel in each frame.
+    pub samples_per_pixel: u32,
+    /// Maximum number of times a ray may scatter before it is considered absorbed.
+    pub max_ray_bounces: u32,
+    pub sampler: Sampler,
+    /// Number of bounces after which paths are randomly terminated based on their throughput,
+    /// with the survivors reweighted to keep the result unbiased. `None` disables it.
+    pub russian_roulette: Option<u32>,
+    /// When set, converged pixels are skipped by later frames. `None` samples every pixel.
+    pub adaptive_sampling: Option<AdaptiveSampling>,
+    /// Finds the objects hit by each ray by traversing a bounding volume hierarchy, instead of
+    /// testing every object.
+    pub bvh: bool,
+    pub background: Background,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            samples_per_pixel: 10,
+            max_ray_bounces: 50,
+            sampler: Sampler::Pcg,
+            russian_roulette: None,
+            adaptive_sampling: None,
+            bvh: true,
+            background: Background::Sky,
+        }
+    }
+}
+
+impl RenderSettings {
+    pub(crate) fn uniform(&self) -> RenderSettingsUniform {
+        RenderSettingsUniform {
+            samples_per_pixel: self.samples_per_pixel.max(1),
+            max_ray_bounces: self.max_ray_bounces,
+            sampler_kind: self.sampler as u32,
+            russian_roulette_depth: self.russian_roulette.unwrap_or(u32::MAX),
+            // A threshold of zero is never reached, so every pixel keeps being sampled.
+            adaptive_threshold: self.adaptive_sampling.map_or(0., |a| a.threshold),
+            adaptive_min_samples: self.adaptive_sampling.map_or(0, |a| a.min_samples),
+            bvh: self.bvh.into(),
+            bvh_padding: 0,
+            background: match self.background {
+                Background::Sky => [0.; 3],
+                Background::Color(color) => color,
+            },
+            sky: (self.background == Background::Sky).into(),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct RenderSettingsUniform {
+    samples_per_pixel: u32,
+    max_ray_bounces: u32,
+    sampler_kind: u32,
+    russian_roulette_depth: u32,
+    adaptive_threshold: f32,
+    adaptive_min_samples: u32,
+    bvh: u32,
+    bvh_padding: u32,
+    background: [f32; 3],
+    sky: u32,
+}
@@ -3,17 +3,23 @@ use std::sync::{Arc, atomic::AtomicBool};
 use gpu_manager::{GpuManager, WindowManager};
 use wgpu::{CommandEncoderDescriptor, wgt::TextureViewDescriptor};
 
-use crate::{ComputeContext, RenderContext, objects::Sphere};
+use crate::{Camera, ComputeContext, RenderContext, RenderSettings, objects::Scene};
 
 pub struct Renderer<'window> {
     gpu_manager: Arc<GpuManager<()>>,
+    compute_context: Arc<ComputeContext>,
     draw_handle: Arc<AtomicBool>,
     window_manager: WindowManager<'window>,
     render_context: RenderContext,
 }
 
 impl<'window> Renderer<'window> {
-    pub fn new(event_loop: &winit::event_loop::ActiveEventLoop, spheres: &[Sphere]) -> Self {
+    pub fn new(
+        event_loop: &winit::event_loop::ActiveEventLoop,
+        scene: &Scene,
+        camera: &Camera,
+        render_settings: &RenderSettings,
+    ) -> Self {
         log::info!("Creating Renderer...");
         log::trace!("Creating GpuManager...");
         let (gpu_manager, window_manager) = pollster::block_on(GpuManager::with_window(event_loop))
@@ -26,7 +32,9 @@ impl<'window> Renderer<'window> {
         let compute_context = ComputeContext::new(
             gpu_manager.device(),
             (window_size.width, window_size.height),
-            spheres,
+            scene,
+            camera,
+            render_settings,
         );
 
         log::trace!("Creating RenderContext...");
@@ -37,15 +45,20 @@ impl<'window> Renderer<'window> {
         );
 
         let gpu_manager = Arc::new(gpu_manager);
+        let compute_context = Arc::new(compute_context);
 
         let draw_handle = Arc::new(AtomicBool::new(true));
 
-        let (draw_handlet, gpu_managert) = (draw_handle.clone(), gpu_manager.clone());
+        let (draw_handlet, gpu_managert, compute_contextt) = (
+            draw_handle.clone(),
+            gpu_manager.clone(),
+            compute_context.clone(),
+        );
         log::trace!("Creating compute thread...");
         let _compute_thread = std::thread::spawn(move || {
             loop {
                 while !draw_handlet.swap(false, std::sync::atomic::Ordering::Acquire) {}
-                run_compute_shader(&gpu_managert, &compute_context);
+                run_compute_shader(&gpu_managert, &compute_contextt);
             }
         });
 
@@ -54,10 +67,20 @@ impl<'window> Renderer<'window> {
             draw_handle,
             window_manager,
             gpu_manager,
+            compute_context,
             render_context,
         }
     }
 
+    /// Moves the camera, restarting the accumulation.
+    pub fn set_camera(&self, camera: &Camera) {
+        self.compute_context
+            .set_camera(self.gpu_manager.queue(), camera);
+        // Make sure the compute thread renders the reset frame even if it is idle.
+        self.draw_handle
+            .store(true, std::sync::atomic::Ordering::Release);
+    }
+
     pub fn render(&self) {
         log::info!("Running render shader...");
         let output = self.window_manager.surface().get_current_texture().unwrap();
@@ -81,6 +104,21 @@ impl<'window> Renderer<'window> {
             .store(true, std::sync::atomic::Ordering::Release);
     }
 
+    /// Changes the render settings, restarting the accumulation.
+    pub fn set_render_settings(&self, render_settings: &RenderSettings) {
+        self.compute_context
+            .set_render_settings(self.gpu_manager.queue(), render_settings);
+        self.draw_handle
+            .store(true, std::sync::atomic::Ordering::Release);
+    }
+
+    /// Switches between the image and the heatmap of the samples per pixel, keeping the
+    /// accumulation.
+    pub fn set_heatmap(&self, heatmap: bool) {
+        self.render_context
+            .set_heatmap(self.gpu_manager.queue(), heatmap);
+    }
+
     pub fn gpu_manager(&self) -> &GpuManager<()> {
         &self.gpu_manager
     }
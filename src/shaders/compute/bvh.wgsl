@@ -0,0 +1,153 @@
+const BVH_SPHERE = 0u;
+const BVH_TRIANGLE = 1u;
+const BVH_QUAD = 2u;
+const BVH_INSTANCE = 3u;
+const BVH_DISK = 4u;
+const BVH_SHAPE = 5u;
+const BVH_KIND_SHIFT = 28u;
+const BVH_INTERIOR = 0xffffffffu;
+// Pushed on the traversal stack when entering an instance, to leave it once it's popped.
+const BVH_INSTANCE_END = 0xffffffffu;
+// Hierarchies are built at most 31 levels deep, so entering an instance from the deepest
+// level of the instances' hierarchy and going down its geometry's never fills the stack.
+const BVH_STACK_SIZE = 64u;
+
+// Nodes are stored depth first, so the left child of an interior node directly follows it.
+struct BvhNode {
+    min: vec3<f32>,
+    right_child: u32,
+    max: vec3<f32>,
+    // Kind and index of the leaf's primitive, or BVH_INTERIOR.
+    primitive: u32,
+};
+
+
+// Traverses the instances' hierarchy from the first node and, on reaching an instance, the
+// hierarchy of its geometry with the ray in the geometry's space.
+fn hit_bvh(ray: Ray, interval: Interval, hit_record: ptr<function, HitRecord>) -> bool {
+    var local_ray = ray;
+    var inverse_direction = 1. / ray.direction;
+    var instance = 0u;
+
+    var temp_rec = HitRecord();
+    var hit_anything = false;
+    var closest_so_far = interval.max;
+    var hit_instance = 0u;
+
+    var stack: array<u32, BVH_STACK_SIZE>;
+    var stack_size = 0u;
+    var node_index = 0u;
+
+    if aabb_distance(bvh_nodes[0], ray.origin, inverse_direction, interval) == F32_MAX {
+        return false;
+    }
+
+    loop {
+        let node = bvh_nodes[node_index];
+        let current = Interval(interval.min, closest_so_far);
+
+        if node.primitive == BVH_INTERIOR {
+            var near = node_index + 1u;
+            var far = node.right_child;
+            var near_distance = aabb_distance(bvh_nodes[near], local_ray.origin, inverse_direction, current);
+            var far_distance = aabb_distance(bvh_nodes[far], local_ray.origin, inverse_direction, current);
+            if far_distance < near_distance {
+                let node = near;
+                near = far;
+                far = node;
+                let distance = near_distance;
+                near_distance = far_distance;
+                far_distance = distance;
+            }
+
+            if far_distance != F32_MAX && stack_size < BVH_STACK_SIZE {
+                stack[stack_size] = far;
+                stack_size++;
+            }
+            if near_distance != F32_MAX {
+                node_index = near;
+                continue;
+            }
+        } else if node.primitive >> BVH_KIND_SHIFT == BVH_INSTANCE {
+            let index = node.primitive & ((1u << BVH_KIND_SHIFT) - 1u);
+            let instance_ray = transform_ray(instances[index], ray);
+            let instance_inverse_direction = 1. / instance_ray.direction;
+            let root = instances[index].bvh_root;
+
+            if aabb_distance(bvh_nodes[root], instance_ray.origin, instance_inverse_direction, current) != F32_MAX && stack_size < BVH_STACK_SIZE {
+                stack[stack_size] = BVH_INSTANCE_END;
+                stack_size++;
+                instance = index;
+                local_ray = instance_ray;
+                inverse_direction = instance_inverse_direction;
+                node_index = root;
+                continue;
+            }
+        } else if hit_primitive(node.primitive, local_ray, current, &temp_rec) {
+            hit_anything = true;
+            closest_so_far = temp_rec.t;
+            *hit_record = temp_rec;
+            hit_instance = instance;
+        }
+
+        // Pop the next node, leaving the instances whose hierarchy was fully traversed.
+        var popped = false;
+        while stack_size != 0u && !popped {
+            stack_size--;
+            if stack[stack_size] == BVH_INSTANCE_END {
+                local_ray = ray;
+                inverse_direction = 1. / ray.direction;
+            } else {
+                node_index = stack[stack_size];
+                popped = true;
+            }
+        }
+        if !popped {
+            break;
+        }
+    }
+
+    if hit_anything {
+        transform_hit_record(instances[hit_instance], hit_record);
+    }
+    return hit_anything;
+}
+
+// Distance along the ray to the node's box, or F32_MAX when the box is missed within `interval`.
+fn aabb_distance(node: BvhNode, origin: vec3<f32>, inverse_direction: vec3<f32>, interval: Interval) -> f32 {
+    let t0 = (node.min - origin) * inverse_direction;
+    let t1 = (node.max - origin) * inverse_direction;
+    let t_min = min(t0, t1);
+    let t_max = max(t0, t1);
+
+    let near = max(max(t_min.x, t_min.y), max(t_min.z, interval.min));
+    let far = min(min(t_max.x, t_max.y), min(t_max.z, interval.max));
+    if near <= far {
+        return near;
+    }
+    return F32_MAX;
+}
+
+fn hit_primitive(reference: u32, ray: Ray, interval: Interval, hit_record: ptr<function, HitRecord>) -> bool {
+    let index = reference & ((1u << BVH_KIND_SHIFT) - 1u);
+    switch reference >> BVH_KIND_SHIFT {
+        case BVH_SPHERE: {
+            return hit_sphere(spheres[index], ray, interval, hit_record);
+        }
+        case BVH_TRIANGLE: {
+            return hit_triangle(triangles[index], ray, interval, hit_record);
+        }
+        case BVH_QUAD: {
+            return hit_quad(quads[index], ray, interval, hit_record);
+        }
+        case BVH_DISK: {
+            return hit_disk(disks[index], ray, interval, hit_record);
+        }
+        case BVH_SHAPE: {
+            return hit_shape(shapes[index], ray, interval, hit_record);
+        }
+        default: {
+            return false;
+        }
+    }
+}
@@ -1,47 +1,96 @@
-const CAMERA_CENTER: vec3<f32> = vec3<f32>(0.0);
+const PERSPECTIVE = 0u;
+const ORTHOGRAPHIC = 1u;
+const FISHEYE = 2u;
+const EQUIRECTANGULAR = 3u;
 
 struct Camera {
+    center: vec3<f32>,
+    shutter_open: f32,
     pix0_coord: vec3<f32>,
+    shutter_close: f32,
     pixel_delta_u: vec3<f32>,
-    pixel_delta_v: vec3<f32>
+    projection: u32,
+    pixel_delta_v: vec3<f32>,
+    defocus_disk_u: vec3<f32>,
+    aperture_blades: u32,
+    defocus_disk_v: vec3<f32>,
+    aperture_rotation: f32,
+    // From the camera center to the center of the focus plane.
+    forward: vec3<f32>,
+    fov: f32,
+    right: vec3<f32>,
+    image_width: f32,
+    up: vec3<f32>,
+    image_height: f32,
 }
 
 fn get_pixel_coord(camera: Camera, invocation_id: vec2<u32>) -> vec3<f32> {
     return camera.pix0_coord + (f32(invocation_id.x) * camera.pixel_delta_u) + (f32(invocation_id.y) * camera.pixel_delta_v);
 }
 
-fn create_camera(size: vec2<u32>) -> Camera {
-    let focal_length = 1.0;
-    let fov = 90.0;
-    let theta = radians(fov);
-    let h = tan(theta / 2.);
-    let viewport_height = 2 * h * focal_length;
-    let viewport_width = viewport_height * (f32(size.x) / f32(size.y));
+// Rays with a zero direction fall outside of the projection and should be discarded.
+fn get_ray(camera: Camera, i: u32, j: u32, pixel_sampler: ptr<function, Sampler>) -> Ray {
+    let offset = sample_2d(pixel_sampler) - 0.5;
+    let ray_time = mix(camera.shutter_open, camera.shutter_close, sample_1d(pixel_sampler));
 
-    let viewport_u = vec3<f32>(viewport_width, 0.0, 0.0);
-    let viewport_v = vec3<f32>(0.0, -viewport_height, 0.0);
+    let pixel_sample = camera.pix0_coord + ((f32(i) + offset.x) * camera.pixel_delta_u) + ((f32(j) + offset.y) * camera.pixel_delta_v);
 
-    let pixel_delta_u = viewport_u / f32(size.x);
-    let pixel_delta_v = viewport_v / f32(size.y);
+    // Image coordinates of the sample, from -1 to 1 along the height of the image.
+    let half_height = camera.image_height / 2.;
+    let image_coord = vec2(
+        (f32(i) + 0.5 + offset.x - camera.image_width / 2.) / half_height,
+        (half_height - f32(j) - 0.5 - offset.y) / half_height
+    );
 
-    let viewport_upper_left = CAMERA_CENTER - vec3<f32>(0., 0., focal_length) - viewport_u / 2. - viewport_v / 2.;
+    let forward = normalize(camera.forward);
 
-    let pix0_coord = viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v);
+    switch camera.projection {
+        case ORTHOGRAPHIC: {
+            return Ray(pixel_sample - camera.forward, camera.forward, ray_time);
+        }
 
-    return Camera(pix0_coord, pixel_delta_u, pixel_delta_v);
-}
+        case FISHEYE: {
+            let radius = length(image_coord);
+            let theta = radius * camera.fov / 2.;
+            if theta > PI {
+                return Ray(camera.center, vec3(0.), ray_time);
+            }
 
-fn get_ray(camera: Camera, i: u32, j: u32, rng_state: ptr<function, u32>) -> Ray {
-    let offset = sample_square(rng_state);
+            var azimuth = vec2(0.);
+            if radius > 0. {
+                azimuth = image_coord / radius;
+            }
+            let direction = sin(theta) * (azimuth.x * camera.right + azimuth.y * camera.up) + cos(theta) * forward;
 
-    let pixel_sample = camera.pix0_coord + ((f32(i) + offset.x) * camera.pixel_delta_u) + ((f32(j) + offset.y) * camera.pixel_delta_v);
+            return Ray(camera.center, direction, ray_time);
+        }
 
-    let ray_origin = CAMERA_CENTER;
-    let ray_direction = pixel_sample - ray_origin;
+        case EQUIRECTANGULAR: {
+            // Longitude spans the width of the image, latitude its height.
+            let longitude = image_coord.x * half_height / camera.image_width * 2. * PI;
+            let latitude = image_coord.y * PI / 2.;
+            let direction = cos(latitude) * (sin(longitude) * camera.right + cos(longitude) * forward) + sin(latitude) * camera.up;
 
-    return Ray(ray_origin, ray_direction);
+            return Ray(camera.center, direction, ray_time);
+        }
+
+        default: {
+            let ray_origin = defocus_sample(camera, pixel_sampler);
+            let ray_direction = pixel_sample - ray_origin;
+
+            return Ray(ray_origin, ray_direction, ray_time);
+        }
+    }
 }
 
-fn sample_square(rng_state: ptr<function, u32>) -> vec2<f32> {
-    return vec2(rngNextFloat(rng_state) - 0.5, rngNextFloat(rng_state) - 0.5);
-} 
\ No newline at end of file
+fn defocus_sample(camera: Camera, pixel_sampler: ptr<function, Sampler>) -> vec3<f32> {
+    let u = sample_2d(pixel_sampler);
+    var p: vec2<f32>;
+    if camera.aperture_blades < 3 {
+        p = square_to_unit_disk(u);
+    } else {
+        p = square_to_polygon(u, camera.aperture_blades, camera.aperture_rotation);
+    }
+
+    return camera.center + (p.x * camera.defocus_disk_u) + (p.y * camera.defocus_disk_v);
+}
\ No newline at end of file
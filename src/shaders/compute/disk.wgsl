@@ -0,0 +1,44 @@
+struct Disk {
+    center: vec3<f32>,
+    // Infinite for planes.
+    radius: f32,
+    normal: vec3<f32>,
+    material: Material,
+};
+
+
+fn hit_disk(disk: Disk, ray: Ray, interval: Interval, hit_record: ptr<function, HitRecord>) -> bool {
+    // Degenerate disks are never hit.
+    if all(disk.normal == vec3(0.)) {
+        return false;
+    }
+    let normal = normalize(disk.normal);
+
+    let denominator = dot(normal, ray.direction);
+    // The ray is parallel to the disk's plane.
+    if abs(denominator) < 1e-8 {
+        return false;
+    }
+
+    let t = dot(normal, disk.center - ray.origin) / denominator;
+    if t <= interval.min || t >= interval.max {
+        return false;
+    }
+
+    let point = ray_at(ray, t);
+    let offset = point - disk.center;
+    if dot(offset, offset) > disk.radius * disk.radius {
+        return false;
+    }
+
+    (*hit_record).t = t;
+    (*hit_record).point = point;
+    (*hit_record).material = disk.material;
+    set_face_normal(hit_record, ray, normal);
+    // Disks are mapped onto the unit square, and planes repeat textures every unit.
+    let basis = orthonormal_basis(normal);
+    let planar = vec2(dot(offset, basis[0]), dot(offset, basis[1]));
+    (*hit_record).uv = select(planar, 0.5 * planar / disk.radius + 0.5, disk.radius <= F32_MAX);
+
+    return true;
+}
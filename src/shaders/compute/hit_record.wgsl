@@ -4,6 +4,8 @@ struct HitRecord {
     normal: vec3<f32>,
     t: f32,
     front_face: bool,
+    // Texture coordinates, with v going up the textures.
+    uv: vec2<f32>,
     material: Material
 };
 
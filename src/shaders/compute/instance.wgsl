@@ -0,0 +1,29 @@
+struct Instance {
+    world_to_object: mat4x4<f32>,
+    object_to_world: mat4x4<f32>,
+    bvh_root: u32,
+    // Ranges of the geometry's objects in the buffers, from the first to one past the last.
+    spheres: vec2<u32>,
+    triangles: vec2<u32>,
+    quads: vec2<u32>,
+    disks: vec2<u32>,
+    // Only the world's objects have planes.
+    planes: vec2<u32>,
+    shapes: vec2<u32>,
+};
+
+
+// The direction isn't normalized, so distances along the ray are the same in both spaces.
+fn transform_ray(instance: Instance, ray: Ray) -> Ray {
+    return Ray(
+        (instance.world_to_object * vec4(ray.origin, 1.)).xyz,
+        (instance.world_to_object * vec4(ray.direction, 0.)).xyz,
+        ray.time
+    );
+}
+
+fn transform_hit_record(instance: Instance, hit_record: ptr<function, HitRecord>) {
+    (*hit_record).point = (instance.object_to_world * vec4((*hit_record).point, 1.)).xyz;
+    // Normals are transformed by the inverse transpose.
+    (*hit_record).normal = normalize((transpose(instance.world_to_object) * vec4((*hit_record).normal, 0.)).xyz);
+}
@@ -1,17 +1,27 @@
-@group(0) @binding(0) var texture: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(0) var texture: texture_storage_2d<rgba32float, write>;
 @group(0) @binding(1) var previous: texture_2d<f32>;
+@group(0) @binding(2) var moments: texture_storage_2d<r32float, write>;
+@group(0) @binding(3) var previous_moments: texture_2d<f32>;
 
 @group(1) @binding(0) var<uniform> frame: u32;
 @group(1) @binding(1) var<storage, read> spheres: array<Sphere>;
+@group(1) @binding(2) var<uniform> camera: Camera;
+@group(1) @binding(3) var<uniform> settings: RenderSettings;
+@group(1) @binding(4) var<storage, read> vertices: array<Vertex>;
+@group(1) @binding(5) var<storage, read> triangles: array<Triangle>;
+@group(1) @binding(6) var<storage, read> bvh_nodes: array<BvhNode>;
+@group(1) @binding(7) var<storage, read> quads: array<Quad>;
+@group(1) @binding(8) var<storage, read> instances: array<Instance>;
+@group(1) @binding(9) var<storage, read> disks: array<Disk>;
+@group(1) @binding(10) var<storage, read> shapes: array<Shape>;
 
+@group(2) @binding(0) var images: texture_2d_array<f32>;
+@group(2) @binding(1) var image_sampler: sampler;
 
-const MAGENTA = vec3(0.74, 0.02, 0.84);
 
-// TODO: make into a uniform
-const SAMPLES_PER_PIXEL = 10u;
-const PIXEL_SAMPLES_SCALE = 1.0 / f32(SAMPLES_PER_PIXEL);
-const MAX_RAY_BOUNCES = 50u;
-const CONTRIBUTION = 0.1f;
+const MAGENTA = vec3(0.74, 0.02, 0.84);
+// Keeps bright paths (e.g. between glass surfaces) from surviving forever.
+const MAX_SURVIVAL_PROBABILITY = 0.95;
 
 @compute @workgroup_size(8,8,1)
 fn main_compute(
@@ -20,78 +30,220 @@ fn main_compute(
 ) {
     let size = textureDimensions(texture).xy;
 
-    var rng_state = initRng(invocation_id.xy, size, frame);
+    var pixel_sampler = init_sampler(settings.sampler_kind, invocation_id.xy, size, frame);
 
-    let camera = create_camera(size);
+    let pixCoord = get_pixel_coord(camera, invocation_id.xy);
 
+    let location = vec2<u32>(u32(invocation_id.x), u32(invocation_id.y));
 
-    let pixCoord = get_pixel_coord(camera, invocation_id.xy);
+    // Linear running sum of every sample, with the number of samples in the alpha channel,
+    // and the running sum of their squared luminance.
+    var accumulated = vec4(0.);
+    var second_moment = 0.;
 
-    var color = vec3(0.);
-    for (var i = 0u; i < SAMPLES_PER_PIXEL; i++) {
-        let ray = get_ray(camera, invocation_id.x, invocation_id.y, &rng_state);
-        color += ray_color(ray, &rng_state);
+    // If this is the first frame, discard whatever was accumulated before.
+    if frame != 0 {
+        accumulated = textureLoad(previous, location, 0);
+        second_moment = textureLoad(previous_moments, location, 0).r;
     }
 
-    let location = vec2<u32>(u32(invocation_id.x), u32(invocation_id.y));
+    var camera_medium = Material();
+    let in_medium = medium_around(camera.center, &camera_medium);
 
-    // sqrt: convert to gamma space
-    let ray_color = vec4<f32>(sqrt(color * PIXEL_SAMPLES_SCALE), 1.0);
+    var samples = settings.samples_per_pixel;
+    if accumulated.a >= f32(settings.adaptive_min_samples) && relative_error(accumulated, second_moment) < settings.adaptive_threshold {
+        samples = 0u;
+    }
+
+    for (var i = 0u; i < samples; i++) {
+        start_sample(&pixel_sampler, frame * settings.samples_per_pixel + i);
+
+        let ray = get_ray(camera, invocation_id.x, invocation_id.y, &pixel_sampler);
+        var color = vec3(0.);
+        if any(ray.direction != vec3(0.)) {
+            color = ray_color(ray, in_medium, camera_medium, &pixel_sampler);
+        }
 
-    let previous_color = textureLoad(previous, location, 0);
+        accumulated += vec4(color, 1.);
+        second_moment += pow(luminance(color), 2.);
+    }
+
+    textureStore(texture, location, accumulated);
+    textureStore(moments, location, vec4(second_moment));
+}
 
-    // If this is the first frame, fully use the ray color.
-    var contribution = CONTRIBUTION;
-    if frame == 0 {
-        contribution = 1f;
+// Standard error of the pixel's mean luminance, relative to that mean.
+fn relative_error(accumulated: vec4<f32>, second_moment: f32) -> f32 {
+    let n = accumulated.a;
+    // Without samples there is no estimate yet, so the pixel isn't converged.
+    if n == 0. {
+        return F32_MAX;
     }
-    let output_color = vec4(mix(previous_color, ray_color, contribution).xyz, 1.);
+    let mean = luminance(accumulated.rgb) / n;
+    let variance = max(second_moment / n - mean * mean, 0.);
 
-    textureStore(texture, location, output_color);
+    return sqrt(variance / n) / (mean + 0.01);
 }
 
 
 
-fn ray_color(ray: Ray, state: ptr<function, u32>) -> vec3<f32> {
+// `in_medium` tells whether the ray starts inside `medium`.
+fn ray_color(ray: Ray, in_medium: bool, medium: Material, pixel_sampler: ptr<function, Sampler>) -> vec3<f32> {
 
     var hit_record = HitRecord();
     var scatter_ray = ScatteredRay();
     var new_ray = ray;
+    var inside = in_medium;
+    var current_medium = medium;
 
-    var color = vec3(1.);
-    for (var bounce = 0u; bounce < MAX_RAY_BOUNCES; bounce++) {
-        if closest_hit(new_ray, Interval(0.001, F32_MAX), &hit_record) {
-            if scatter(new_ray, hit_record, hit_record.material, &scatter_ray, state) {
-                color *= scatter_ray.attenuation;
-                new_ray = scatter_ray.ray;
-               // return vec3(f32(hit_record.material.fuzziness));
-            } else {
-                return vec3(0.);
+    // Light gathered along the path, and the fraction of the light at the path's end that
+    // reaches the camera.
+    var color = vec3(0.);
+    var throughput = vec3(1.);
+    for (var bounce = 0u; bounce < settings.max_ray_bounces; bounce++) {
+        if !closest_hit(new_ray, Interval(0.001, F32_MAX), &hit_record) {
+            return color + throughput * background(new_ray);
+        }
+
+        // Inside a medium, the path may scatter before getting to the next surface.
+        if !inside || !scatter_in_medium(new_ray, hit_record.t, current_medium, &scatter_ray, pixel_sampler) {
+            hit_record.material.albedo = textured_albedo(hit_record);
+            color += throughput * emitted(hit_record, hit_record.material);
+            if hit_record.material.ty == MEDIUM {
+                inside = hit_record.front_face;
+                current_medium = hit_record.material;
+            }
+            if !scatter(new_ray, hit_record, hit_record.material, &scatter_ray, pixel_sampler) {
+                return color;
             }
-            continue;
-        } else {
-            let unit_direction = normalize(new_ray.direction);
-            let a = 0.5 * (unit_direction.y + 1.0);
-            color *= mix(vec3<f32>(1.0), vec3<f32>(0.5, 0.7, 1.0), a);
-            return color;
+        }
+
+        throughput *= scatter_ray.attenuation;
+        new_ray = scatter_ray.ray;
+       // return vec3(f32(hit_record.material.fuzziness));
+
+        if bounce >= settings.russian_roulette_depth {
+            let survival = min(max(throughput.r, max(throughput.g, throughput.b)), MAX_SURVIVAL_PROBABILITY);
+            if sample_1d(pixel_sampler) >= survival {
+                return color;
+            }
+            throughput /= survival;
         }
     }
-    return vec3(0.);
+    return color;
 
 }
 
+// Whether `point` is inside a medium, found by following a ray from it through the surfaces
+// until it crosses the boundary of one.
+fn medium_around(point: vec3<f32>, medium: ptr<function, Material>) -> bool {
+    var hit_record = HitRecord();
+    var ray = Ray(point, vec3(0., 1., 0.), 0.);
+    for (var i = 0u; i < settings.max_ray_bounces; i++) {
+        if !closest_hit(ray, Interval(0.001, F32_MAX), &hit_record) {
+            return false;
+        }
+        if hit_record.material.ty == MEDIUM {
+            *medium = hit_record.material;
+            (*medium).albedo = textured_albedo(hit_record);
+            return !hit_record.front_face;
+        }
+        ray.origin = hit_record.point;
+    }
+    return false;
+}
+
+// Light coming from outside of the scene.
+fn background(ray: Ray) -> vec3<f32> {
+    if settings.sky == 0u {
+        return settings.background;
+    }
+    let unit_direction = normalize(ray.direction);
+    let a = 0.5 * (unit_direction.y + 1.0);
+    return mix(vec3<f32>(1.0), vec3<f32>(0.5, 0.7, 1.0), a);
+}
+
 fn closest_hit(ray: Ray, interval: Interval, hit_record: ptr<function, HitRecord>) -> bool {
     var temp_rec = HitRecord();
     var hit_anything = false;
     var closest_so_far = interval.max;
+    var hit_instance = 0u;
+
+    if settings.bvh != 0u {
+        // Planes are unbounded, so they're tested outside of the hierarchy. They're in the
+        // world's space, which needs no transform.
+        let planes = instances[0].planes;
+        for (var j = planes.x; j < planes.y; j++) {
+            if hit_disk(disks[j], ray, Interval(interval.min, closest_so_far), &temp_rec) {
+                hit_anything = true;
+                closest_so_far = temp_rec.t;
+                *hit_record = temp_rec;
+            }
+        }
+        return hit_bvh(ray, Interval(interval.min, closest_so_far), hit_record) || hit_anything;
+    }
+
+    for (var i = 0u; i < arrayLength(&instances); i++) {
+        let instance = instances[i];
+        let local_ray = transform_ray(instance, ray);
+
+        for (var j = instance.spheres.x; j < instance.spheres.y; j++) {
+            if hit_sphere(spheres[j], local_ray, Interval(interval.min, closest_so_far), &temp_rec) {
+                hit_anything = true;
+                closest_so_far = temp_rec.t;
+                *hit_record = temp_rec;
+                hit_instance = i;
+            }
+        }
+
+        for (var j = instance.triangles.x; j < instance.triangles.y; j++) {
+            if hit_triangle(triangles[j], local_ray, Interval(interval.min, closest_so_far), &temp_rec) {
+                hit_anything = true;
+                closest_so_far = temp_rec.t;
+                *hit_record = temp_rec;
+                hit_instance = i;
+            }
+        }
 
-    for (var i = 0u; i < arrayLength(&spheres); i++) {
-        if hit_sphere(spheres[i], ray, Interval(interval.min, closest_so_far), &temp_rec) {
-            hit_anything = true;
-            closest_so_far = temp_rec.t;
-            *hit_record = temp_rec;
+        for (var j = instance.quads.x; j < instance.quads.y; j++) {
+            if hit_quad(quads[j], local_ray, Interval(interval.min, closest_so_far), &temp_rec) {
+                hit_anything = true;
+                closest_so_far = temp_rec.t;
+                *hit_record = temp_rec;
+                hit_instance = i;
+            }
+        }
+
+        for (var j = instance.disks.x; j < instance.disks.y; j++) {
+            if hit_disk(disks[j], local_ray, Interval(interval.min, closest_so_far), &temp_rec) {
+                hit_anything = true;
+                closest_so_far = temp_rec.t;
+                *hit_record = temp_rec;
+                hit_instance = i;
+            }
+        }
+
+        for (var j = instance.planes.x; j < instance.planes.y; j++) {
+            if hit_disk(disks[j], local_ray, Interval(interval.min, closest_so_far), &temp_rec) {
+                hit_anything = true;
+                closest_so_far = temp_rec.t;
+                *hit_record = temp_rec;
+                hit_instance = i;
+            }
+        }
+
+        for (var j = instance.shapes.x; j < instance.shapes.y; j++) {
+            if hit_shape(shapes[j], local_ray, Interval(interval.min, closest_so_far), &temp_rec) {
+                hit_anything = true;
+                closest_so_far = temp_rec.t;
+                *hit_record = temp_rec;
+                hit_instance = i;
+            }
         }
     }
 
+    if hit_anything {
+        transform_hit_record(instances[hit_instance], hit_record);
+    }
     return hit_anything;
-}
\ No newline at end of file
+}
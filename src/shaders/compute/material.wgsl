@@ -1,13 +1,31 @@
 const LAMBERTIAN = 0u;
 const METAL = 1u;
 const DIELETRIC = 2u;
+const MEDIUM = 3u;
+const EMISSIVE = 4u;
+const PRINCIPLED = 5u;
+// Roughness under which microfacets are too sharp to be represented by floats.
+const MIN_GGX_ALPHA = 1e-3;
 
 
 struct Material {
     ty: u32,
+    // Fuzziness of metals, or roughness of principled materials.
     fuzziness: f32,
+    // Refractive index of dielectrics, or of the specular layer of principled materials.
     refractive_index: f32,
+    density: f32,
     albedo: vec3<f32>,
+    anisotropy: f32,
+    // Radiance emitted by emissive materials, relative to their albedo.
+    intensity: f32,
+    texture: u32,
+    // Layer of the image texture.
+    image: u32,
+    // Size of the procedural texture's features, and the color its pattern blends into.
+    scale: f32,
+    color: vec3<f32>,
+    metallic: f32,
 }
 
 struct ScatteredRay {
@@ -15,25 +33,25 @@ struct ScatteredRay {
     attenuation: vec3<f32>
 }
 
-fn scatter(ray: Ray, hit_record: HitRecord, material: Material, scattered: ptr<function, ScatteredRay>, rng_state: ptr<function, u32>) -> bool {
+fn scatter(ray: Ray, hit_record: HitRecord, material: Material, scattered: ptr<function, ScatteredRay>, pixel_sampler: ptr<function, Sampler>) -> bool {
     switch material.ty {
         case LAMBERTIAN: {
-            var scatter_direction = hit_record.normal + rngUnitVector(rng_state);
+            var scatter_direction = hit_record.normal + square_to_unit_vector(sample_2d(pixel_sampler));
 
             if near_zero(scatter_direction) {
                 scatter_direction = hit_record.normal;
             }
 
-            (*scattered).ray = Ray(hit_record.point, scatter_direction);
+            (*scattered).ray = Ray(hit_record.point, scatter_direction, ray.time);
             (*scattered).attenuation = material.albedo;
             return true;
         }
 
         case METAL: {
             var reflected = reflect(ray.direction, hit_record.normal);
-            reflected = normalize(reflected) + (material.fuzziness * rngUnitVector(rng_state));
+            reflected = normalize(reflected) + (material.fuzziness * square_to_unit_vector(sample_2d(pixel_sampler)));
 
-            (*scattered).ray = Ray(hit_record.point, reflected);
+            (*scattered).ray = Ray(hit_record.point, reflected, ray.time);
             (*scattered).attenuation = material.albedo;
             return dot((*scattered).ray.direction, hit_record.normal) > 0.;
         }
@@ -51,17 +69,28 @@ fn scatter(ray: Ray, hit_record: HitRecord, material: Material, scattered: ptr<f
             let cannot_refract = ri * sin_theta > 1.0;
 
             var direction: vec3<f32>;
-            if cannot_refract || reflectance(cos_theta, ri) > rngNextFloat(rng_state) {
+            if cannot_refract || reflectance(cos_theta, ri) > sample_1d(pixel_sampler) {
                 direction = reflect(unit_direction, hit_record.normal);
             } else {
                 direction = refract(unit_direction, hit_record.normal, ri);
             }
 
-            (*scattered).ray = Ray(hit_record.point, direction);
+            (*scattered).ray = Ray(hit_record.point, direction, ray.time);
 
             return true;
         }
 
+        case MEDIUM: {
+            // Rays go through the boundary, the path then scatters inside the medium in
+            // `scatter_in_medium`.
+            (*scattered).ray = Ray(hit_record.point, ray.direction, ray.time);
+            (*scattered).attenuation = vec3(1.);
+            return true;
+        }
+
+        case PRINCIPLED: {
+            return scatter_principled(ray, hit_record, material, scattered, pixel_sampler);
+        }
 
         default: {
             return false;
@@ -69,7 +98,129 @@ fn scatter(ray: Ray, hit_record: HitRecord, material: Material, scattered: ptr<f
     }
 }
 
+// Samples the distance the ray travels through the medium before scattering. Returns false
+// when it gets to the next surface, `t_max` along the ray, first.
+fn scatter_in_medium(ray: Ray, t_max: f32, medium: Material, scattered: ptr<function, ScatteredRay>, pixel_sampler: ptr<function, Sampler>) -> bool {
+    let distance = -log(1. - sample_1d(pixel_sampler)) / medium.density;
+    let speed = length(ray.direction);
+    if distance >= t_max * speed {
+        return false;
+    }
+
+    let direction = sample_henyey_greenstein(ray.direction / speed, medium.anisotropy, sample_2d(pixel_sampler));
+    (*scattered).ray = Ray(ray_at(ray, distance / speed), direction, ray.time);
+    (*scattered).attenuation = medium.albedo;
+    return true;
+}
+
+// Light emitted towards the ray by the surface it hit.
+fn emitted(hit_record: HitRecord, material: Material) -> vec3<f32> {
+    if material.ty == EMISSIVE && hit_record.front_face {
+        return material.intensity * material.albedo;
+    }
+    return vec3(0.);
+}
+
 fn reflectance(cosine: f32, refractive_index: f32) -> f32 {
     var r0 = pow((1.0 - refractive_index) / (1.0 + refractive_index), 2.0);
     return fma(1.0 - r0, pow(1. - cosine, 5.), r0);
-}
\ No newline at end of file
+}
+
+// Cook-Torrance microfacets with the GGX distribution over a lambertian base. Directions are
+// sampled from either the normals of the microfacets visible from the ray or the diffuse
+// lobe, and weighted by their probability under both.
+fn scatter_principled(ray: Ray, hit_record: HitRecord, material: Material, scattered: ptr<function, ScatteredRay>, pixel_sampler: ptr<function, Sampler>) -> bool {
+    // Directions are in the frame of the surface, whose normal is Z.
+    let basis = orthonormal_basis(hit_record.normal);
+    let to_view = -normalize(ray.direction);
+    // Shading normals may face away from the ray.
+    let view = normalize(vec3(dot(to_view, basis[0]), dot(to_view, basis[1]), max(dot(to_view, hit_record.normal), 1e-4)));
+
+    // glTF and Blender roughnesses are perceptual, the square root of GGX's alpha.
+    let alpha = max(material.fuzziness * material.fuzziness, MIN_GGX_ALPHA);
+    let dielectric_reflectance = pow((material.refractive_index - 1.) / (material.refractive_index + 1.), 2.);
+    let normal_reflectance = mix(vec3(dielectric_reflectance), material.albedo, material.metallic);
+    let specular_probability = mix(clamp(luminance(fresnel_schlick(normal_reflectance, view.z)), 0.25, 0.9), 1., material.metallic);
+
+    let u = sample_2d(pixel_sampler);
+    var light = vec3(0., 0., 1.) + square_to_unit_vector(u);
+    if sample_1d(pixel_sampler) < specular_probability {
+        light = reflect(-view, sample_ggx_visible_normal(view, alpha, u));
+    }
+    if light.z <= 0. || near_zero(light) {
+        return false;
+    }
+    light = normalize(light);
+
+    let half_vector = normalize(view + light);
+    let fresnel = fresnel_schlick(normal_reflectance, dot(view, half_vector));
+    let distribution = ggx_distribution(half_vector.z, alpha);
+    let specular = distribution * ggx_visibility(view.z, light.z, alpha) * fresnel;
+    let diffuse = (1. - material.metallic) * (1. - fresnel) * material.albedo / PI;
+
+    // Visible normals are reflected into directions with the density D * G1 / (4 * n.v).
+    let specular_pdf = distribution * smith_g1(view.z, alpha) / (4. * view.z);
+    let pdf = mix(light.z / PI, specular_pdf, specular_probability);
+
+    (*scattered).ray = Ray(hit_record.point, mat3x3(basis[0], basis[1], hit_record.normal) * light, ray.time);
+    (*scattered).attenuation = (specular + diffuse) * light.z / pdf;
+    return true;
+}
+
+// Sampling the visible normals with spherical caps, from "Sampling Visible GGX Normals with
+// Spherical Caps" by Dupuy and Benyoub.
+fn sample_ggx_visible_normal(view: vec3<f32>, alpha: f32, u: vec2<f32>) -> vec3<f32> {
+    // Stretching the view direction turns the microfacets into a hemisphere.
+    let stretched = normalize(vec3(alpha * view.xy, view.z));
+    let phi = 2. * PI * u.x;
+    let z = (1. - u.y) * (1. + stretched.z) - stretched.z;
+    let sin_theta = sqrt(clamp(1. - z * z, 0., 1.));
+    let normal = vec3(sin_theta * cos(phi), sin_theta * sin(phi), z) + stretched;
+    return normalize(vec3(alpha * normal.xy, normal.z));
+}
+
+// Density of the microfacets with the given cosine to the normal.
+fn ggx_distribution(cosine: f32, alpha: f32) -> f32 {
+    let alpha_squared = alpha * alpha;
+    let denominator = cosine * cosine * (alpha_squared - 1.) + 1.;
+    return alpha_squared / (PI * denominator * denominator);
+}
+
+// Fraction of the microfacets visible from a direction.
+fn smith_g1(cosine: f32, alpha: f32) -> f32 {
+    let alpha_squared = alpha * alpha;
+    return 2. * cosine / (cosine + sqrt(alpha_squared + (1. - alpha_squared) * cosine * cosine));
+}
+
+// Height correlated masking and shadowing, divided by the 4 * n.v * n.l of the BRDF.
+fn ggx_visibility(view_cosine: f32, light_cosine: f32, alpha: f32) -> f32 {
+    let alpha_squared = alpha * alpha;
+    let view = light_cosine * sqrt(alpha_squared + (1. - alpha_squared) * view_cosine * view_cosine);
+    let light = view_cosine * sqrt(alpha_squared + (1. - alpha_squared) * light_cosine * light_cosine);
+    return 0.5 / (view + light);
+}
+
+fn fresnel_schlick(normal_reflectance: vec3<f32>, cosine: f32) -> vec3<f32> {
+    return normal_reflectance + (1. - normal_reflectance) * pow(1. - saturate(cosine), 5.);
+}
+
+// Direction scattered from `direction` according to the Henyey-Greenstein phase function
+// with asymmetry `g`.
+fn sample_henyey_greenstein(direction: vec3<f32>, g: f32, u: vec2<f32>) -> vec3<f32> {
+    var cos_theta = 1. - 2. * u.x;
+    if abs(g) > 1e-3 {
+        let ratio = (1. - g * g) / (1. - g + 2. * g * u.x);
+        cos_theta = (1. + g * g - ratio * ratio) / (2. * g);
+    }
+    let sin_theta = sqrt(max(0., 1. - cos_theta * cos_theta));
+    let phi = 2. * PI * u.y;
+
+    // Orthonormal basis around the direction.
+    let sign = select(-1., 1., direction.z >= 0.);
+    let a = -1. / (sign + direction.z);
+    let b = direction.x * direction.y * a;
+    let tangent = vec3(1. + sign * direction.x * direction.x * a, sign * b, -sign * direction.x);
+    let bitangent = vec3(b, sign + direction.y * direction.y * a, -direction.y);
+
+    return sin_theta * (cos(phi) * tangent + sin(phi) * bitangent) + cos_theta * direction;
+}
@@ -12,3 +12,14 @@ fn near_zero(vector: vec3<f32>) -> bool {
     let v = abs(vector);
     return all(vec3(v.x < s, v.y < s, v.z < s));
 }
+
+// Two unit vectors perpendicular to each other and to the unit vector `normal`.
+fn orthonormal_basis(normal: vec3<f32>) -> mat2x3<f32> {
+    let helper = select(vec3(1., 0., 0.), vec3(0., 1., 0.), abs(normal.x) > 0.9);
+    let tangent = normalize(cross(normal, helper));
+    return mat2x3(tangent, cross(normal, tangent));
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3(0.2126, 0.7152, 0.0722));
+}
@@ -0,0 +1,45 @@
+struct Quad {
+    origin: vec3<f32>,
+    u: vec3<f32>,
+    v: vec3<f32>,
+    material: Material,
+};
+
+
+fn hit_quad(quad: Quad, ray: Ray, interval: Interval, hit_record: ptr<function, HitRecord>) -> bool {
+    let n = cross(quad.u, quad.v);
+    // Degenerate quads are never hit.
+    if all(n == vec3(0.)) {
+        return false;
+    }
+    let normal = normalize(n);
+
+    let denominator = dot(normal, ray.direction);
+    // The ray is parallel to the quad's plane.
+    if abs(denominator) < 1e-8 {
+        return false;
+    }
+
+    let t = dot(normal, quad.origin - ray.origin) / denominator;
+    if t <= interval.min || t >= interval.max {
+        return false;
+    }
+
+    // Coordinates of the hit point along the quad's sides.
+    let point = ray_at(ray, t);
+    let planar = point - quad.origin;
+    let w = n / dot(n, n);
+    let alpha = dot(w, cross(planar, quad.v));
+    let beta = dot(w, cross(quad.u, planar));
+    if alpha < 0. || alpha > 1. || beta < 0. || beta > 1. {
+        return false;
+    }
+
+    (*hit_record).t = t;
+    (*hit_record).point = point;
+    (*hit_record).material = quad.material;
+    set_face_normal(hit_record, ray, normal);
+    (*hit_record).uv = vec2(alpha, beta);
+
+    return true;
+}
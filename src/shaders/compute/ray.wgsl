@@ -1,6 +1,7 @@
 struct Ray {
     origin: vec3<f32>,
-    direction: vec3<f32>
+    direction: vec3<f32>,
+    time: f32
 }
 
 fn ray_at(ray: Ray, t: f32) -> vec3<f32> {
@@ -0,0 +1,119 @@
+const PCG_SAMPLER = 0u;
+const SOBOL_SAMPLER = 1u;
+
+// Hands out the random numbers of a pixel's samples, one dimension at a time.
+struct Sampler {
+    kind: u32,
+    // PCG stream of the pixel in the current frame.
+    rng_state: u32,
+    // Per-pixel seed that scrambles the Sobol sequence.
+    seed: u32,
+    // Index of the current sample among all samples taken for the pixel.
+    index: u32,
+    dimension: u32,
+}
+
+fn init_sampler(kind: u32, pixel: vec2<u32>, resolution: vec2<u32>, frame: u32) -> Sampler {
+    let seed = hash_combine(0x2545f491u, dot(pixel, vec2<u32>(1u, resolution.x)));
+    return Sampler(kind, initRng(pixel, resolution, frame), seed, 0u, 0u);
+}
+
+fn start_sample(pixel_sampler: ptr<function, Sampler>, index: u32) {
+    (*pixel_sampler).index = index;
+    (*pixel_sampler).dimension = 0u;
+}
+
+fn sample_1d(pixel_sampler: ptr<function, Sampler>) -> f32 {
+    return sample_2d(pixel_sampler).x;
+}
+
+fn sample_2d(pixel_sampler: ptr<function, Sampler>) -> vec2<f32> {
+    let dimension = (*pixel_sampler).dimension;
+    (*pixel_sampler).dimension += 1u;
+
+    switch (*pixel_sampler).kind {
+        case SOBOL_SAMPLER: {
+            // Every pair of dimensions uses a differently shuffled and scrambled copy of
+            // the first two Sobol dimensions.
+            return sobol_2d((*pixel_sampler).index, hash_combine((*pixel_sampler).seed, dimension));
+        }
+
+        default: {
+            return vec2(rngNextFloat(&(*pixel_sampler).rng_state), rngNextFloat(&(*pixel_sampler).rng_state));
+        }
+    }
+}
+
+// Shuffled, Owen scrambled 2D Sobol point.
+// Based on "Practical Hash-based Owen Scrambling" (Burley, 2020)
+fn sobol_2d(index: u32, seed: u32) -> vec2<f32> {
+    let shuffled = nested_uniform_scramble(index, seed);
+
+    var x = reverseBits(shuffled);
+    var y = 0u;
+    var direction = 1u << 31u;
+    for (var i = shuffled; i != 0u; i >>= 1u) {
+        if (i & 1u) != 0u {
+            y ^= direction;
+        }
+        direction ^= direction >> 1u;
+    }
+
+    x = nested_uniform_scramble(x, hash_combine(seed, 0u));
+    y = nested_uniform_scramble(y, hash_combine(seed, 1u));
+
+    return vec2(f32(x >> 8u), f32(y >> 8u)) / f32(1u << 24u);
+}
+
+fn hash_combine(seed: u32, value: u32) -> u32 {
+    return seed ^ (jenkinsHash(value) + 0x9e3779b9u + (seed << 6u) + (seed >> 2u));
+}
+
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    return reverseBits(laine_karras_permutation(reverseBits(x), seed));
+}
+
+fn laine_karras_permutation(input: u32, seed: u32) -> u32 {
+    var x = input + seed;
+    x ^= x * 0x6c50b47cu;
+    x ^= x * 0xb82f1e52u;
+    x ^= x * 0xc7afe638u;
+    x ^= x * 0x8d22f6e6u;
+    return x;
+}
+
+fn square_to_unit_vector(u: vec2<f32>) -> vec3<f32> {
+    let z = 1. - 2. * u.x;
+    let r = sqrt(max(0., 1. - z * z));
+    let phi = 2. * PI * u.y;
+
+    return vec3(r * cos(phi), r * sin(phi), z);
+}
+
+fn square_to_unit_disk(u: vec2<f32>) -> vec2<f32> {
+    let r = sqrt(u.x);
+    let phi = 2. * PI * u.y;
+
+    return vec2(r * cos(phi), r * sin(phi));
+}
+
+// Uniform point inside a regular polygon inscribed in the unit circle.
+fn square_to_polygon(u: vec2<f32>, sides: u32, rotation: f32) -> vec2<f32> {
+    let sector_angle = 2. * PI / f32(sides);
+    let scaled = u.x * f32(sides);
+    let sector = min(u32(scaled), sides - 1u);
+
+    let angle_a = rotation + f32(sector) * sector_angle;
+    let a = vec2(cos(angle_a), sin(angle_a));
+    let b = vec2(cos(angle_a + sector_angle), sin(angle_a + sector_angle));
+
+    // Fold the unit square onto the triangle (center, a, b).
+    var s = scaled - f32(sector);
+    var t = u.y;
+    if s + t > 1. {
+        s = 1. - s;
+        t = 1. - t;
+    }
+
+    return s * a + t * b;
+}
\ No newline at end of file
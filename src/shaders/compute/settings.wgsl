@@ -0,0 +1,12 @@
+struct RenderSettings {
+    samples_per_pixel: u32,
+    max_ray_bounces: u32,
+    sampler_kind: u32,
+    russian_roulette_depth: u32,
+    adaptive_threshold: f32,
+    adaptive_min_samples: u32,
+    bvh: u32,
+    // Color of the background, unless the sky is drawn.
+    background: vec3<f32>,
+    sky: u32,
+}
\ No newline at end of file
@@ -0,0 +1,563 @@
+const SHAPE_CONE = 0u;
+const SHAPE_TORUS = 1u;
+const SHAPE_SPHERE = 2u;
+const SHAPE_CUBOID = 3u;
+// Solid made with CSG, whose nodes are a range of other shapes.
+const SHAPE_CSG = 4u;
+// Operations combining the two previous solids in a CSG solid's nodes.
+const CSG_UNION = 5u;
+const CSG_INTERSECTION = 6u;
+const CSG_DIFFERENCE = 7u;
+// Surfaces of its primitives a ray may cross before the CSG solid is considered missed.
+const CSG_MAX_CROSSINGS = 128u;
+// Distance past a crossing from which the next one is searched, so that a root found only
+// approximately, like a torus', isn't crossed again.
+const CSG_EPSILON = 1e-4;
+// Signed distance field, whose nodes are a range of other shapes.
+const SHAPE_SDF = 8u;
+// Nodes of a signed distance field besides spheres, cuboids and tori.
+const SDF_SMOOTH_UNION = 9u;
+const SDF_REPEAT = 10u;
+const SDF_REPEAT_END = 11u;
+// Sizes of the stacks of distances and of points before repetitions.
+const SDF_STACK_SIZE = 16u;
+const SDF_MAX_REPETITIONS = 4u;
+const SDF_MAX_STEPS = 128u;
+// Distance to the field under which the surface is reached.
+const SDF_EPSILON = 1e-4;
+// Offset of the samples the normal is estimated from.
+const SDF_NORMAL_OFFSET = 1e-4;
+const TORUS_BISECTIONS = 24u;
+
+struct Shape {
+    // Center of the base, of the torus or of the sphere, minimum corner of the cuboid or of
+    // the distance field's bounds, or repetitions on each side of a repetition.
+    origin: vec3<f32>,
+    // Radius of the base, major radius of the torus, radius of the sphere, or smoothness of
+    // the smooth union.
+    radius: f32,
+    // From the base to the top, normal of the torus' plane, size of the cuboid, maximum corner
+    // of the distance field's bounds, or spacing of a repetition.
+    axis: vec3<f32>,
+    // Radius of the top, or minor radius of the torus.
+    second_radius: f32,
+    kind: u32,
+    // Range of a CSG solid's or a distance field's nodes, stored in postfix order.
+    nodes: vec2<u32>,
+    material: Material,
+    // Center of the field a repetition copies, around which its copies are placed.
+    center: vec3<f32>,
+};
+
+
+fn hit_shape(shape: Shape, ray: Ray, interval: Interval, hit_record: ptr<function, HitRecord>) -> bool {
+    if shape.kind == SHAPE_CSG {
+        return hit_csg(shape, ray, interval, hit_record);
+    }
+    if shape.kind == SHAPE_SDF {
+        return hit_sdf(shape, ray, interval, hit_record);
+    }
+    return hit_primitive_shape(shape, ray, interval, hit_record);
+}
+
+fn hit_primitive_shape(shape: Shape, ray: Ray, interval: Interval, hit_record: ptr<function, HitRecord>) -> bool {
+    var t = 0.;
+    var outward_normal = vec3(0.);
+    var hit = false;
+    switch shape.kind {
+        case SHAPE_CONE: {
+            hit = hit_cone(shape, ray, interval, &t, &outward_normal);
+        }
+        case SHAPE_TORUS: {
+            hit = hit_torus(shape, ray, interval, &t, &outward_normal);
+        }
+        case SHAPE_SPHERE: {
+            return hit_sphere(Sphere(shape.origin, shape.radius, shape.material, vec3(0.)), ray, interval, hit_record);
+        }
+        case SHAPE_CUBOID: {
+            hit = hit_cuboid(shape, ray, interval, &t, &outward_normal);
+        }
+        default: {}
+    }
+    if !hit {
+        return false;
+    }
+
+    (*hit_record).t = t;
+    (*hit_record).point = ray_at(ray, t);
+    (*hit_record).material = shape.material;
+    set_face_normal(hit_record, ray, outward_normal);
+    (*hit_record).uv = shape_uv(shape, (*hit_record).point, outward_normal);
+
+    return true;
+}
+
+// Cones and tori have the angle around their axis as u, and either the height along the axis
+// or the angle around the tube as v. Cuboids are mapped face by face.
+fn shape_uv(shape: Shape, point: vec3<f32>, outward_normal: vec3<f32>) -> vec2<f32> {
+    let offset = point - shape.origin;
+    if shape.kind == SHAPE_CUBOID {
+        let corner = offset / shape.axis;
+        let normal = abs(outward_normal);
+        return select(select(corner.xy, corner.xz, normal.y > 0.5), corner.zy, normal.x > 0.5);
+    }
+
+    let axis = normalize(shape.axis);
+    let basis = orthonormal_basis(axis);
+    let y = dot(offset, axis);
+    let radial = offset - y * axis;
+    let u = atan2(dot(radial, basis[1]), dot(radial, basis[0])) / (2. * PI) + 0.5;
+    if shape.kind == SHAPE_TORUS {
+        return vec2(u, atan2(y, length(radial) - shape.radius) / (2. * PI) + 0.5);
+    }
+    return vec2(u, y / length(shape.axis));
+}
+
+// Capped cone, whose radius changes linearly from the base to the top.
+fn hit_cone(shape: Shape, ray: Ray, interval: Interval, t: ptr<function, f32>, outward_normal: ptr<function, vec3<f32>>) -> bool {
+    // Degenerate shapes are never hit.
+    if all(shape.axis == vec3(0.)) {
+        return false;
+    }
+    let height = length(shape.axis);
+    let axis = shape.axis / height;
+    let slope = (shape.second_radius - shape.radius) / height;
+
+    // Height along the axis and offset from it, at the ray's origin and per unit of t.
+    let offset = ray.origin - shape.origin;
+    let y = dot(offset, axis);
+    let y_speed = dot(ray.direction, axis);
+    let radial = offset - y * axis;
+    let radial_speed = ray.direction - y_speed * axis;
+    // Radius of the cone at the height of the ray's origin.
+    let radius = shape.radius + slope * y;
+    let radius_speed = slope * y_speed;
+
+    var closest_so_far = interval.max;
+
+    // The side is where the offset from the axis is as long as the radius.
+    let a = dot(radial_speed, radial_speed) - radius_speed * radius_speed;
+    let h = dot(radial, radial_speed) - radius * radius_speed;
+    let c = dot(radial, radial) - radius * radius;
+    let discriminant = h * h - a * c;
+    if a != 0. && discriminant >= 0. {
+        let disc_root = sqrt(discriminant);
+        for (var i = 0u; i < 2u; i++) {
+            let root = (-h + select(-disc_root, disc_root, i == 1u)) / a;
+            let root_y = y + root * y_speed;
+            if root > interval.min && root < closest_so_far && root_y >= 0. && root_y <= height {
+                closest_so_far = root;
+                let root_radial = radial + root * radial_speed;
+                let root_radius = radius + root * radius_speed;
+                *outward_normal = normalize(root_radial - root_radius * slope * axis);
+            }
+        }
+    }
+
+    // Flat caps at the base and the top.
+    for (var i = 0u; i < 2u; i++) {
+        let cap_radius = select(shape.radius, shape.second_radius, i == 1u);
+        let root = (f32(i) * height - y) / y_speed;
+        let root_radial = radial + root * radial_speed;
+        if root > interval.min && root < closest_so_far && dot(root_radial, root_radial) <= cap_radius * cap_radius {
+            closest_so_far = root;
+            *outward_normal = select(-axis, axis, i == 1u);
+        }
+    }
+
+    *t = closest_so_far;
+    return closest_so_far < interval.max;
+}
+
+// Finds the first root of the torus' quartic along the ray, within its bounding sphere.
+fn hit_torus(shape: Shape, ray: Ray, interval: Interval, t: ptr<function, f32>, outward_normal: ptr<function, vec3<f32>>) -> bool {
+    if all(shape.axis == vec3(0.)) {
+        return false;
+    }
+    let axis = normalize(shape.axis);
+    let major = shape.radius;
+    let minor = shape.second_radius;
+
+    // The quartic is solved along the unit direction, so distances are converted to and from t.
+    let speed = length(ray.direction);
+    let direction = ray.direction / speed;
+    let offset = ray.origin - shape.origin;
+
+    let bound = abs(major) + abs(minor);
+    let b = dot(offset, direction);
+    let discriminant = b * b - dot(offset, offset) + bound * bound;
+    if discriminant < 0. {
+        return false;
+    }
+    let start = max(-b - sqrt(discriminant), interval.min * speed);
+    let end = min(-b + sqrt(discriminant), interval.max * speed);
+    if start >= end {
+        return false;
+    }
+
+    // Coefficients of the quartic in the distance from the start, where the ray is moved to
+    // keep them small: (|p|² + R² - r²)² - 4R²(|p|² - (p·axis)²) = 0.
+    let p = offset + start * direction;
+    let k = dot(p, p) + major * major - minor * minor;
+    let g = 2. * dot(p, direction);
+    let h0 = dot(p, axis);
+    let h1 = dot(direction, axis);
+    let m = 4. * major * major;
+    let coefficients = vec4(
+        k * k - m * (dot(p, p) - h0 * h0),
+        2. * g * k - m * (g - 2. * h0 * h1),
+        g * g + 2. * k - m * (1. - h1 * h1),
+        2. * g,
+    );
+
+    // Between the extrema of the quartic it's monotonic, so each piece holds at most one root.
+    let span = end - start;
+    var extrema = clamp(
+        solve_cubic(0.75 * coefficients.w, 0.5 * coefficients.z, 0.25 * coefficients.y),
+        vec3(0.),
+        vec3(span)
+    );
+    extrema = vec3(min(extrema.x, extrema.y), max(extrema.x, extrema.y), extrema.z);
+    extrema = vec3(extrema.x, min(extrema.y, extrema.z), max(extrema.y, extrema.z));
+    extrema = vec3(min(extrema.x, extrema.y), max(extrema.x, extrema.y), extrema.z);
+    var bounds = array(extrema.x, extrema.y, extrema.z, span);
+
+    var lower = 0.;
+    var f_lower = quartic(coefficients, lower);
+    for (var i = 0u; i < 4u; i++) {
+        var upper = bounds[i];
+        let f_upper = quartic(coefficients, upper);
+        if (f_lower < 0.) != (f_upper < 0.) {
+            for (var j = 0u; j < TORUS_BISECTIONS; j++) {
+                let middle = 0.5 * (lower + upper);
+                let f_middle = quartic(coefficients, middle);
+                if (f_middle < 0.) == (f_lower < 0.) {
+                    lower = middle;
+                    f_lower = f_middle;
+                } else {
+                    upper = middle;
+                }
+            }
+            let root = 0.5 * (lower + upper);
+            *t = (start + root) / speed;
+
+            // Away from the closest point of the circle of radius R.
+            let point = p + root * direction;
+            let radial = point - dot(point, axis) * axis;
+            *outward_normal = normalize(point - major * normalize(radial));
+            return true;
+        }
+        lower = upper;
+        f_lower = f_upper;
+    }
+    return false;
+}
+
+fn hit_cuboid(shape: Shape, ray: Ray, interval: Interval, t: ptr<function, f32>, outward_normal: ptr<function, vec3<f32>>) -> bool {
+    if all(shape.axis == vec3(0.)) {
+        return false;
+    }
+
+    // Distances to the planes of the faces, the box being between the last one entered and
+    // the first one left.
+    let inverse_direction = 1. / ray.direction;
+    let t0 = (shape.origin - ray.origin) * inverse_direction;
+    let t1 = (shape.origin + shape.axis - ray.origin) * inverse_direction;
+    let near = min(t0, t1);
+    let far = max(t0, t1);
+    let t_near = max(max(near.x, near.y), near.z);
+    let t_far = min(min(far.x, far.y), far.z);
+    if t_near > t_far {
+        return false;
+    }
+
+    if t_near > interval.min && t_near < interval.max {
+        *t = t_near;
+        *outward_normal = normalize(-sign(ray.direction) * vec3<f32>(near == vec3(t_near)));
+        return true;
+    }
+    if t_far > interval.min && t_far < interval.max {
+        *t = t_far;
+        *outward_normal = normalize(sign(ray.direction) * vec3<f32>(far == vec3(t_far)));
+        return true;
+    }
+    return false;
+}
+
+// Whether the point is inside the primitive shape.
+fn shape_contains(shape: Shape, point: vec3<f32>) -> bool {
+    let offset = point - shape.origin;
+    switch shape.kind {
+        case SHAPE_CONE: {
+            let height = length(shape.axis);
+            let y = dot(offset, shape.axis) / height;
+            let radial = offset - y * shape.axis / height;
+            let radius = mix(shape.radius, shape.second_radius, y / height);
+            return y >= 0. && y <= height && dot(radial, radial) <= radius * radius;
+        }
+        case SHAPE_TORUS: {
+            let axis = normalize(shape.axis);
+            let major = shape.radius;
+            let k = dot(offset, offset) + major * major - shape.second_radius * shape.second_radius;
+            let y = dot(offset, axis);
+            return k * k < 4. * major * major * (dot(offset, offset) - y * y);
+        }
+        case SHAPE_SPHERE: {
+            return dot(offset, offset) <= shape.radius * shape.radius;
+        }
+        case SHAPE_CUBOID: {
+            return all(offset >= vec3(0.)) && all(offset <= shape.axis);
+        }
+        default: {
+            return false;
+        }
+    }
+}
+
+// Walks through the surfaces of the CSG solid's primitives along the ray, keeping track of
+// which primitives the ray is in, until crossing one changes whether it's in the solid.
+fn hit_csg(csg: Shape, ray: Ray, interval: Interval, hit_record: ptr<function, HitRecord>) -> bool {
+    // Bit i is set when the ray is inside the solid's primitive i.
+    var inside = 0u;
+    let start = ray_at(ray, interval.min);
+    var primitive = 0u;
+    for (var i = csg.nodes.x; i < csg.nodes.y; i++) {
+        if shapes[i].kind < CSG_UNION {
+            if shape_contains(shapes[i], start) {
+                inside |= 1u << primitive;
+            }
+            primitive++;
+        }
+    }
+    let was_inside = csg_contains(csg, inside);
+
+    var temp_rec = HitRecord();
+    var t_min = interval.min;
+    for (var crossing = 0u; crossing < CSG_MAX_CROSSINGS; crossing++) {
+        var closest_so_far = interval.max;
+        var crossed = 0u;
+        primitive = 0u;
+        for (var i = csg.nodes.x; i < csg.nodes.y; i++) {
+            if shapes[i].kind < CSG_UNION {
+                if hit_primitive_shape(shapes[i], ray, Interval(t_min, closest_so_far), &temp_rec) {
+                    closest_so_far = temp_rec.t;
+                    *hit_record = temp_rec;
+                    crossed = 1u << primitive;
+                }
+                primitive++;
+            }
+        }
+        if crossed == 0u {
+            return false;
+        }
+
+        inside ^= crossed;
+        let is_inside = csg_contains(csg, inside);
+        if is_inside != was_inside {
+            // The normal is already against the ray, and the front face is the one entering
+            // the solid.
+            (*hit_record).front_face = is_inside;
+            return true;
+        }
+        t_min = closest_so_far + CSG_EPSILON / length(ray.direction);
+    }
+    return false;
+}
+
+// Whether the primitives whose bits are set in `inside` make the ray inside the CSG solid,
+// evaluating its nodes with a stack of bits.
+fn csg_contains(csg: Shape, inside: u32) -> bool {
+    var stack = 0u;
+    var primitive = 0u;
+    for (var i = csg.nodes.x; i < csg.nodes.y; i++) {
+        let kind = shapes[i].kind;
+        if kind < CSG_UNION {
+            stack = (stack << 1u) | ((inside >> primitive) & 1u);
+            primitive++;
+            continue;
+        }
+
+        let b = stack & 1u;
+        let a = (stack >> 1u) & 1u;
+        var result = 0u;
+        switch kind {
+            case CSG_UNION: {
+                result = a | b;
+            }
+            case CSG_INTERSECTION: {
+                result = a & b;
+            }
+            default: {
+                result = a & (b ^ 1u);
+            }
+        }
+        stack = ((stack >> 2u) << 1u) | result;
+    }
+    return (stack & 1u) != 0u;
+}
+
+// Sphere tracing, stepping along the ray by the distance to the field until reaching its
+// surface.
+fn hit_sdf(sdf: Shape, ray: Ray, interval: Interval, hit_record: ptr<function, HitRecord>) -> bool {
+    // Only the part of the ray within the field's bounds is traced.
+    let inverse_direction = 1. / ray.direction;
+    let t0 = (sdf.origin - ray.origin) * inverse_direction;
+    let t1 = (sdf.axis - ray.origin) * inverse_direction;
+    let near = min(t0, t1);
+    let far = max(t0, t1);
+    var t = max(max(near.x, near.y), max(near.z, interval.min));
+    let t_max = min(min(far.x, far.y), min(far.z, interval.max));
+    if t > t_max {
+        return false;
+    }
+
+    // The direction isn't normalized in instances.
+    let speed = length(ray.direction);
+    var leaf = 0u;
+    // Sign of the field on the ray's side of the surface. Rays leaving the surface, like the
+    // ones it scatters, step away from it until knowing which side they're on.
+    var side = 0.;
+    var hit = false;
+    for (var step = 0u; step < SDF_MAX_STEPS; step++) {
+        let distance = sdf_distance(sdf, ray_at(ray, t), &leaf);
+        if side == 0. {
+            if abs(distance) >= SDF_EPSILON {
+                side = sign(distance);
+            }
+        } else if side * distance < SDF_EPSILON {
+            hit = true;
+            break;
+        }
+
+        t += max(side * distance, SDF_EPSILON) / speed;
+        if t > t_max {
+            return false;
+        }
+    }
+    if !hit {
+        return false;
+    }
+
+    (*hit_record).t = t;
+    (*hit_record).point = ray_at(ray, t);
+    (*hit_record).material = shapes[leaf].material;
+    let normal = sdf_normal(sdf, (*hit_record).point);
+    set_face_normal(hit_record, ray, normal);
+    // Fields have no parametrization, so their textures are mapped by orientation.
+    (*hit_record).uv = sphere_uv(normal);
+    return true;
+}
+
+// Gradient of the field, from the differences between samples at the corners of a
+// tetrahedron around the point.
+fn sdf_normal(sdf: Shape, point: vec3<f32>) -> vec3<f32> {
+    let corners = array(vec3(1., -1., -1.), vec3(-1., -1., 1.), vec3(-1., 1., -1.), vec3(1., 1., 1.));
+    var gradient = vec3(0.);
+    var leaf = 0u;
+    for (var i = 0u; i < 4u; i++) {
+        gradient += corners[i] * sdf_distance(sdf, point + corners[i] * SDF_NORMAL_OFFSET, &leaf);
+    }
+    return normalize(gradient);
+}
+
+// Signed distance from the point to the field, evaluating its nodes with a stack of distances.
+// `leaf` is set to the index of the closest primitive, which gives the surface's material.
+fn sdf_distance(sdf: Shape, point: vec3<f32>, leaf: ptr<function, u32>) -> f32 {
+    var distances: array<f32, SDF_STACK_SIZE>;
+    var leaves: array<u32, SDF_STACK_SIZE>;
+    var size = 0u;
+    // Points before each of the repetitions being evaluated.
+    var points: array<vec3<f32>, SDF_MAX_REPETITIONS>;
+    var repetitions = 0u;
+    var p = point;
+
+    for (var i = sdf.nodes.x; i < sdf.nodes.y; i++) {
+        let node = shapes[i];
+        switch node.kind {
+            case SDF_SMOOTH_UNION: {
+                let a = distances[size - 2u];
+                let b = distances[size - 1u];
+                // Polynomial smooth minimum, lowering the distance by up to a quarter of the
+                // smoothness where both are close.
+                let k = node.radius;
+                let h = select(0., max(k - abs(a - b), 0.) / k, k > 0.);
+                distances[size - 2u] = min(a, b) - h * h * k / 4.;
+                if b < a {
+                    leaves[size - 2u] = leaves[size - 1u];
+                }
+                size--;
+            }
+            case SDF_REPEAT: {
+                points[repetitions] = p;
+                repetitions++;
+                // Moves the point to the nearest copy, on the axes that are repeated.
+                let cell = clamp(round((p - node.center) / node.axis), -node.origin, node.origin);
+                p -= select(vec3(0.), node.axis * cell, node.axis != vec3(0.));
+            }
+            case SDF_REPEAT_END: {
+                repetitions--;
+                p = points[repetitions];
+            }
+            default: {
+                distances[size] = primitive_distance(node, p);
+                leaves[size] = i;
+                size++;
+            }
+        }
+    }
+
+    *leaf = leaves[0];
+    return distances[0];
+}
+
+// Signed distance from the point to a sphere, cuboid or torus.
+fn primitive_distance(shape: Shape, point: vec3<f32>) -> f32 {
+    switch shape.kind {
+        case SHAPE_SPHERE: {
+            return length(point - shape.origin) - shape.radius;
+        }
+        case SHAPE_CUBOID: {
+            let half_size = shape.axis / 2.;
+            let q = abs(point - shape.origin - half_size) - half_size;
+            return length(max(q, vec3(0.))) + min(max(q.x, max(q.y, q.z)), 0.);
+        }
+        case SHAPE_TORUS: {
+            let offset = point - shape.origin;
+            let axis = normalize(shape.axis);
+            let y = dot(offset, axis);
+            let radial = length(offset - y * axis) - shape.radius;
+            return length(vec2(radial, y)) - shape.second_radius;
+        }
+        default: {
+            return F32_MAX;
+        }
+    }
+}
+
+// x⁴ + c.w x³ + c.z x² + c.y x + c.x, for the coefficients c.
+fn quartic(coefficients: vec4<f32>, x: f32) -> f32 {
+    return (((x + coefficients.w) * x + coefficients.z) * x + coefficients.y) * x + coefficients.x;
+}
+
+// Real roots of x³ + a x² + b x + c, repeated when there are fewer than three.
+fn solve_cubic(a: f32, b: f32, c: f32) -> vec3<f32> {
+    // Substituting x = y - a / 3 gives y³ + p y + q = 0.
+    let shift = a / 3.;
+    let p = b - a * shift;
+    let q = shift * (2. * shift * shift - b) + c;
+
+    let discriminant = q * q / 4. + p * p * p / 27.;
+    if discriminant >= 0. {
+        let root = sqrt(discriminant);
+        return vec3(cbrt(-q / 2. + root) + cbrt(-q / 2. - root) - shift);
+    }
+
+    // Three real roots, found with trigonometry.
+    let r = sqrt(-p / 3.);
+    let phi = acos(clamp(-q / (2. * r * r * r), -1., 1.)) / 3.;
+    return 2. * r * cos(vec3(phi, phi - 2. * PI / 3., phi - 4. * PI / 3.)) - shift;
+}
+
+fn cbrt(x: f32) -> f32 {
+    return sign(x) * pow(abs(x), 1. / 3.);
+}
@@ -3,11 +3,19 @@ struct Sphere {
     center: vec3<f32>,
     radius: f32,
     material: Material,
+    velocity: vec3<f32>,
 };
 
+// Spheres rest at their start before time 0 and at their end after time 1, so they stay within
+// the bounds of their movement whatever the shutter interval.
+fn sphere_center(sphere: Sphere, time: f32) -> vec3<f32> {
+    return sphere.center + saturate(time) * sphere.velocity;
+}
+
 
 fn hit_sphere(sphere: Sphere, ray: Ray, interval: Interval, hit_record: ptr<function, HitRecord>) -> bool {
-    let oc = sphere.center - ray.origin;
+    let center = sphere_center(sphere, ray.time);
+    let oc = center - ray.origin;
     let a = length_squared(ray.direction);
     let h = dot(ray.direction, oc);
     let c = length_squared(oc) - pow(sphere.radius, 2.);
@@ -31,8 +39,17 @@ fn hit_sphere(sphere: Sphere, ray: Ray, interval: Interval, hit_record: ptr<func
     (*hit_record).t = root;
     (*hit_record).point = ray_at(ray, root);
     (*hit_record).material = sphere.material;
-    let outward_normal = ((*hit_record).point - sphere.center) / sphere.radius;
+    let outward_normal = ((*hit_record).point - center) / sphere.radius;
     set_face_normal(hit_record, ray, outward_normal);
+    (*hit_record).uv = sphere_uv(outward_normal);
 
     return true;
+}
+
+// Longitude and latitude of a point on the unit sphere, u starting from -X around Y and v
+// going from -Y to +Y.
+fn sphere_uv(point: vec3<f32>) -> vec2<f32> {
+    let theta = acos(clamp(-point.y, -1., 1.));
+    let phi = atan2(-point.z, point.x) + PI;
+    return vec2(phi / (2. * PI), theta / PI);
 }
\ No newline at end of file
@@ -0,0 +1,82 @@
+// What changes the albedo across surfaces.
+const SOLID_COLOR = 0u;
+const IMAGE_TEXTURE = 1u;
+const CHECKER_TEXTURE = 2u;
+const TURBULENCE_TEXTURE = 3u;
+const MARBLE_TEXTURE = 4u;
+const WOOD_TEXTURE = 5u;
+const NOISE_TEXTURE = 6u;
+const NOISE_OCTAVES = 7u;
+
+
+// Albedo at the hit point, scaled by an image or blended into the texture's color by a
+// procedural pattern.
+fn textured_albedo(hit_record: HitRecord) -> vec3<f32> {
+    let material = hit_record.material;
+    if material.texture == SOLID_COLOR {
+        return material.albedo;
+    }
+    if material.texture == IMAGE_TEXTURE {
+        // Images are stored from their top row.
+        let uv = vec2(hit_record.uv.x, 1. - hit_record.uv.y);
+        return material.albedo * textureSampleLevel(images, image_sampler, uv, material.image, 0.).rgb;
+    }
+
+    // Procedural textures are solid, filling the space the surfaces are cut from.
+    let point = hit_record.point / material.scale;
+    let cell = floor(point);
+    var pattern = f32(i32(cell.x + cell.y + cell.z) & 1);
+    if material.texture != CHECKER_TEXTURE {
+        // Signed for plain noise, and turbulence otherwise.
+        let turbulence = fractal_noise(point, material.texture != NOISE_TEXTURE);
+        switch material.texture {
+            case NOISE_TEXTURE: {
+                pattern = 0.5 * (1. + turbulence);
+            }
+            case MARBLE_TEXTURE: {
+                pattern = 0.5 * (1. + sin(point.x + 5. * turbulence));
+            }
+            case WOOD_TEXTURE: {
+                pattern = fract(length(point.xz) + 0.5 * turbulence);
+            }
+            default: {
+                pattern = turbulence;
+            }
+        }
+    }
+    return mix(material.albedo, material.color, saturate(pattern));
+}
+
+// Sum of octaves of noise, each twice as detailed and half as strong as the previous one.
+// Turbulence sums their magnitudes instead, folding the noise into creases.
+fn fractal_noise(point: vec3<f32>, turbulence: bool) -> f32 {
+    var sum = 0.;
+    var octave_point = point;
+    var weight = 1.;
+    for (var octave = 0u; octave < NOISE_OCTAVES; octave++) {
+        let noise = perlin(octave_point);
+        sum += weight * select(noise, abs(noise), turbulence);
+        octave_point *= 2.;
+        weight *= 0.5;
+    }
+    return sum;
+}
+
+// Perlin gradient noise, interpolated between random gradients at the corners of unit cubes.
+fn perlin(point: vec3<f32>) -> f32 {
+    let cell = floor(point);
+    let offset = point - cell;
+    // Quintic fade, whose second derivative is continuous across cells.
+    let fade = offset * offset * offset * (offset * (offset * 6. - 15.) + 10.);
+
+    var noise = 0.;
+    for (var i = 0u; i < 8u; i++) {
+        let corner = vec3(f32(i & 1u), f32((i >> 1u) & 1u), f32(i >> 2u));
+        let lattice = vec3<u32>(vec3<i32>(cell + corner));
+        let hash = jenkinsHash(lattice.x ^ jenkinsHash(lattice.y ^ jenkinsHash(lattice.z)));
+        let gradient = square_to_unit_vector(vec2(f32(hash & 0xffffu), f32(hash >> 16u)) / 65536.);
+        let weights = mix(1. - fade, fade, corner);
+        noise += weights.x * weights.y * weights.z * dot(gradient, offset - corner);
+    }
+    return noise;
+}
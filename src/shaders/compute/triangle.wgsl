@@ -0,0 +1,63 @@
+struct Vertex {
+    position: vec3<f32>,
+    // Texture coordinates, stored in the padding of the vectors.
+    u: f32,
+    // Zero when the mesh is flat shaded.
+    normal: vec3<f32>,
+    v: f32,
+};
+
+struct Triangle {
+    vertices: vec3<u32>,
+    material: Material,
+};
+
+
+// Möller–Trumbore ray-triangle intersection.
+fn hit_triangle(triangle: Triangle, ray: Ray, interval: Interval, hit_record: ptr<function, HitRecord>) -> bool {
+    let v0 = vertices[triangle.vertices.x];
+    let v1 = vertices[triangle.vertices.y];
+    let v2 = vertices[triangle.vertices.z];
+
+    let edge1 = v1.position - v0.position;
+    let edge2 = v2.position - v0.position;
+    let p = cross(ray.direction, edge2);
+    let determinant = dot(edge1, p);
+
+    // The ray is parallel to the triangle, or the triangle is degenerate.
+    if determinant == 0. {
+        return false;
+    }
+    let inverse_determinant = 1. / determinant;
+
+    let s = ray.origin - v0.position;
+    let u = dot(s, p) * inverse_determinant;
+    if u < 0. || u > 1. {
+        return false;
+    }
+
+    let q = cross(s, edge1);
+    let v = dot(ray.direction, q) * inverse_determinant;
+    if v < 0. || u + v > 1. {
+        return false;
+    }
+
+    let t = dot(edge2, q) * inverse_determinant;
+    if t <= interval.min || t >= interval.max {
+        return false;
+    }
+
+    (*hit_record).t = t;
+    (*hit_record).point = ray_at(ray, t);
+    (*hit_record).material = triangle.material;
+    set_face_normal(hit_record, ray, normalize(cross(edge1, edge2)));
+    (*hit_record).uv = (1. - u - v) * vec2(v0.u, v0.v) + u * vec2(v1.u, v1.v) + v * vec2(v2.u, v2.v);
+
+    let shading_normal = (1. - u - v) * v0.normal + u * v1.normal + v * v2.normal;
+    if !near_zero(shading_normal) {
+        // Keep the shading normal on the side the ray came from.
+        (*hit_record).normal = select(-1., 1., (*hit_record).front_face) * normalize(shading_normal);
+    }
+
+    return true;
+}
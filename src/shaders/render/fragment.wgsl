@@ -1,4 +1,7 @@
 @group(0) @binding(0) var texture: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> settings: RenderSettings;
+@group(0) @binding(2) var<uniform> frame: u32;
+@group(0) @binding(3) var<uniform> heatmap_view: u32;
 
 
 struct VertexOutput {
@@ -8,5 +11,19 @@ struct VertexOutput {
 
 @fragment
 fn main_fragment(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureLoad(texture, vec2(u32(in.clip_position.x), u32(in.clip_position.y)), 0);
+    let accumulated = textureLoad(texture, vec2(u32(in.clip_position.x), u32(in.clip_position.y)), 0);
+
+    if heatmap_view != 0u {
+        // Share of the samples the pixel would have received without adaptive sampling.
+        let max_samples = f32(frame + 1u) * f32(settings.samples_per_pixel);
+        return vec4(heatmap(clamp(accumulated.a / max_samples, 0., 1.)), 1.);
+    }
+
+    // sqrt: convert the mean of the samples to gamma space
+    return vec4(sqrt(accumulated.rgb / max(accumulated.a, 1.)), 1.);
+}
+
+// Blue for few samples, through green, to red for many.
+fn heatmap(t: f32) -> vec3<f32> {
+    return clamp(vec3(2. * t - 1., 1. - abs(2. * t - 1.), 1. - 2. * t), vec3(0.), vec3(1.));
 }
\ No newline at end of file
@@ -1,12 +1,17 @@
 use std::{f32::consts::PI, path::Path};
 
+use glam::{Mat4, Quat, Vec3};
 use gpu_manager::GpuManager;
 use pollster::FutureExt;
 use wgpu::{CommandEncoderDescriptor, TextureFormat};
 
 use crate::{
+    AdaptiveSampling, Background, Camera, Projection, RenderSettings, Sampler,
     compute_context::ComputeContext,
-    objects::{Sphere, material},
+    objects::{
+        self, Cone, Csg, Cylinder, Disk, Geometry, Instance, Mesh, Plane, Quad, Scene, Sdf, Sphere,
+        Texture, Torus, material,
+    },
     render_context::RenderContext,
 };
 
@@ -38,7 +43,13 @@ const SPHERES: [Sphere; 5] = [
 fn create_compute_context() {
     let gpu_manager = GpuManager::simple().block_on().unwrap();
 
-    let compute_ctx = ComputeContext::new(gpu_manager.device(), (100, 100), &SPHERES);
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        (100, 100),
+        &Scene::new(SPHERES.to_vec()),
+        &Camera::default(),
+        &RenderSettings::default(),
+    );
 
     dbg!(compute_ctx);
 }
@@ -46,7 +57,13 @@ fn create_compute_context() {
 #[test]
 fn create_render_context() {
     let gpu_manager = GpuManager::simple().block_on().unwrap();
-    let compute_ctx = ComputeContext::new(gpu_manager.device(), (100, 100), &SPHERES);
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        (100, 100),
+        &Scene::new(SPHERES.to_vec()),
+        &Camera::default(),
+        &RenderSettings::default(),
+    );
 
     let render_ctx = RenderContext::new(
         gpu_manager.device(),
@@ -57,11 +74,98 @@ fn create_render_context() {
     dbg!(render_ctx);
 }
 
+#[test]
+fn heatmap_keeps_accumulation() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        (64, 64),
+        &Scene::new(SPHERES.to_vec()),
+        &Camera::default(),
+        &RenderSettings {
+            samples_per_pixel: 8,
+            max_ray_bounces: 8,
+            adaptive_sampling: Some(AdaptiveSampling {
+                threshold: 0.05,
+                min_samples: 16,
+            }),
+            ..Default::default()
+        },
+    );
+    let render_ctx = RenderContext::new(
+        gpu_manager.device(),
+        &compute_ctx,
+        TextureFormat::Rgba8Unorm,
+    );
+
+    for _ in 0..6 {
+        let mut encoder = gpu_manager
+            .device()
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Test Encoder"),
+            });
+        compute_ctx.draw(&mut encoder, gpu_manager.queue());
+        gpu_manager.queue().submit(Some(encoder.finish()));
+    }
+    let accumulated = read_texture(&gpu_manager, &compute_ctx.output_texture);
+
+    let target = gpu_manager
+        .device()
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("Test Heatmap Texture"),
+            size: compute_ctx.output_texture.size(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+    render_ctx.set_heatmap(gpu_manager.queue(), true);
+    let mut encoder = gpu_manager
+        .device()
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Test Encoder"),
+        });
+    render_ctx.draw_to_texture(
+        &mut encoder,
+        &target.create_view(&wgpu::TextureViewDescriptor::default()),
+    );
+    gpu_manager.queue().submit(Some(encoder.finish()));
+    let heatmap = read_texture(&gpu_manager, &target);
+
+    // Pixels still sampled every frame, like the glass, are red, while the ones that converged
+    // early, like the sky, have no red at all.
+    let (mut unconverged, mut converged) = (0, 0);
+    for (texel, color) in accumulated.iter().zip(&heatmap) {
+        if texel[3] == 48. {
+            assert_eq!(color, &[1., 0., 0., 1.]);
+            unconverged += 1;
+        } else if texel[3] <= 24. {
+            assert_eq!(color[0], 0., "{} samples show as {color:?}", texel[3]);
+            converged += 1;
+        }
+    }
+    assert!(unconverged > 0 && converged > 0);
+
+    assert_eq!(render_ctx.frame(), 6);
+    assert_eq!(
+        read_texture(&gpu_manager, &compute_ctx.output_texture),
+        accumulated
+    );
+}
+
 #[test]
 fn draw_scene() {
     let gpu_manager = GpuManager::simple().block_on().unwrap();
 
-    let compute_ctx = ComputeContext::new(gpu_manager.device(), (100, 100), &SPHERES);
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        (100, 100),
+        &Scene::new(SPHERES.to_vec()),
+        &Camera::default(),
+        &RenderSettings::default(),
+    );
 
     let mut encoder = gpu_manager
         .device()
@@ -80,7 +184,9 @@ fn render_materials_to_file() {
         gpu_manager.device(),
         // Width must be a multiple of 128
         (128, 128),
-        &SPHERES,
+        &Scene::new(SPHERES.to_vec()),
+        &Camera::default(),
+        &RenderSettings::default(),
     );
 
     let mut encoder = gpu_manager
@@ -125,7 +231,9 @@ fn render_fov_to_file() {
         gpu_manager.device(),
         // Width must be a multiple of 128
         (128, 128),
-        &spheres,
+        &Scene::new(spheres),
+        &Camera::default(),
+        &RenderSettings::default(),
     );
 
     let mut encoder = gpu_manager
@@ -155,7 +263,9 @@ fn render_multiple_frames_materials() {
         gpu_manager.device(),
         // Width must be a multiple of 128
         (128, 128),
-        &SPHERES,
+        &Scene::new(SPHERES.to_vec()),
+        &Camera::default(),
+        &RenderSettings::default(),
     );
 
     for i in 0u32..60 {
@@ -205,7 +315,9 @@ fn render_multiple_frames_fov() {
         gpu_manager.device(),
         // Width must be a multiple of 128
         (128, 128),
-        &spheres,
+        &Scene::new(spheres),
+        &Camera::default(),
+        &RenderSettings::default(),
     );
 
     for i in 0u32..10 {
@@ -232,3 +344,1927 @@ fn render_multiple_frames_fov() {
         .is_ok()
     );
 }
+
+#[test]
+fn render_look_at_camera_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        // Width must be a multiple of 128
+        (128, 128),
+        &Scene::new(SPHERES.to_vec()),
+        &Camera::new([-2., 2., 1.], [0., 0., -1.], [0., 1., 0.], 20.),
+        &RenderSettings::default(),
+    );
+
+    let mut encoder = gpu_manager
+        .device()
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Test Encoder"),
+        });
+
+    compute_ctx.draw(&mut encoder, gpu_manager.queue());
+    gpu_manager.queue().submit(Some(encoder.finish()));
+
+    compute_ctx.set_camera(
+        gpu_manager.queue(),
+        &Camera::new([2., 0.5, 1.], [0., 0., -1.], [0., 1., 0.], 45.),
+    );
+
+    let mut encoder = gpu_manager
+        .device()
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Test Encoder"),
+        });
+
+    compute_ctx.draw(&mut encoder, gpu_manager.queue());
+    gpu_manager.queue().submit(Some(encoder.finish()));
+    // The draw after the change is the first frame again.
+    assert_eq!(
+        compute_ctx.frame.load(std::sync::atomic::Ordering::Acquire),
+        1
+    );
+
+    assert!(
+        super::write_to_file(
+            &gpu_manager,
+            &compute_ctx.previous_texture,
+            Some(Path::new("one_frame_look_at_test.png"))
+        )
+        .is_ok()
+    );
+}
+
+#[test]
+fn render_defocus_blur_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let render = |focus_distance| {
+        let compute_ctx = ComputeContext::new(
+            gpu_manager.device(),
+            // Width must be a multiple of 128
+            (128, 128),
+            &Scene::new(SPHERES.to_vec()),
+            &Camera::new([-2., 2., 1.], [0., 0., -1.], [0., 1., 0.], 20.)
+                .with_defocus(0.6, focus_distance)
+                .with_aperture_blades(6, 15.),
+            &RenderSettings::default(),
+        );
+
+        for _ in 0..10 {
+            let mut encoder =
+                gpu_manager
+                    .device()
+                    .create_command_encoder(&CommandEncoderDescriptor {
+                        label: Some("Test Encoder"),
+                    });
+            compute_ctx.draw(&mut encoder, gpu_manager.queue());
+            gpu_manager.queue().submit(Some(encoder.finish()));
+        }
+        compute_ctx
+    };
+
+    let focused = render(3.4);
+    assert!(
+        super::write_to_file(
+            &gpu_manager,
+            &focused.output_texture,
+            Some(Path::new("multiple_frames_defocus_test.png"))
+        )
+        .is_ok()
+    );
+    // Focusing on the metal sphere behind blurs the blue one in front of it.
+    let unfocused = render(4.1);
+
+    // Rows between the sky and the top of the blue sphere that are neither, in the columns
+    // over its top.
+    let edge_rows = |compute_ctx: &ComputeContext| {
+        let pixels = read_texture(&gpu_manager, &compute_ctx.output_texture);
+        let blue = |x: usize, y: usize| {
+            let [r, _, b, a] = pixels[y * 128 + x];
+            (b - r) / a
+        };
+        (30..70)
+            .map(|x| {
+                let (sky, sphere) = (blue(x, 0), blue(x, 31));
+                (0..32)
+                    .filter(|&y| {
+                        let t = (blue(x, y) - sky) / (sphere - sky);
+                        (0.25..0.75).contains(&t)
+                    })
+                    .count()
+            })
+            .sum::<usize>()
+    };
+    let (focused, unfocused) = (edge_rows(&focused), edge_rows(&unfocused));
+    assert!(
+        2 * focused < unfocused,
+        "the edge spans {focused} rows in focus and {unfocused} out of focus"
+    );
+}
+
+#[test]
+fn camera_controller_moves_and_zooms() {
+    use winit::{event::ElementState, keyboard::KeyCode};
+
+    let mut controller = crate::controls::CameraController::default();
+    let mut camera = Camera::default();
+
+    assert!(!controller.update(&mut camera, 1.));
+
+    controller.process_keyboard(KeyCode::KeyW, ElementState::Pressed);
+    assert!(controller.update(&mut camera, 1.));
+    assert!(camera.look_from[2] < 0.);
+    assert_eq!(camera.look_at[2] - camera.look_from[2], -1.);
+
+    controller.process_keyboard(KeyCode::KeyW, ElementState::Released);
+    assert!(!controller.update(&mut camera, 1.));
+
+    assert!(controller.process_mouse_motion(100., 0., &mut camera));
+    assert!(camera.look_at[0] > camera.look_from[0]);
+
+    assert!(controller.process_scroll(1., &mut camera));
+    assert!(camera.vfov < 90.);
+}
+
+#[test]
+fn render_with_runtime_settings_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let preview = RenderSettings {
+        samples_per_pixel: 1,
+        max_ray_bounces: 4,
+        ..Default::default()
+    };
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        // Width must be a multiple of 128
+        (128, 128),
+        &Scene::new(SPHERES.to_vec()),
+        &Camera::default(),
+        &preview,
+    );
+
+    let mut encoder = gpu_manager
+        .device()
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Test Encoder"),
+        });
+    compute_ctx.draw(&mut encoder, gpu_manager.queue());
+    gpu_manager.queue().submit(Some(encoder.finish()));
+
+    compute_ctx.set_render_settings(
+        gpu_manager.queue(),
+        &RenderSettings {
+            samples_per_pixel: 64,
+            max_ray_bounces: 512,
+            ..Default::default()
+        },
+    );
+
+    let mut encoder = gpu_manager
+        .device()
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Test Encoder"),
+        });
+    compute_ctx.draw(&mut encoder, gpu_manager.queue());
+    gpu_manager.queue().submit(Some(encoder.finish()));
+    // The draw after the change is the first frame again.
+    assert_eq!(
+        compute_ctx.frame.load(std::sync::atomic::Ordering::Acquire),
+        1
+    );
+
+    assert!(
+        super::write_to_file(
+            &gpu_manager,
+            &compute_ctx.previous_texture,
+            Some(Path::new("one_frame_settings_test.png"))
+        )
+        .is_ok()
+    );
+    // None of the samples from before the change are left.
+    let texels = read_texture(&gpu_manager, &compute_ctx.previous_texture);
+    assert!(texels.iter().all(|texel| texel[3] <= 64.));
+}
+
+/// Reads back an `Rgba32Float` or `Rgba8Unorm` texture.
+fn read_texture(gpu_manager: &GpuManager, texture: &wgpu::Texture) -> Vec<[f32; 4]> {
+    let texel_size = texture.format().block_copy_size(None).unwrap();
+    let bytes_per_row =
+        (texel_size * texture.width()).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let buffer = gpu_manager.device().create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Test Readback Buffer"),
+        size: (bytes_per_row * texture.height()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = gpu_manager
+        .device()
+        .create_command_encoder(&CommandEncoderDescriptor::default());
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(texture.height()),
+            },
+        },
+        texture.size(),
+    );
+    gpu_manager.queue().submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    gpu_manager.device().poll(wgpu::PollType::Wait).unwrap();
+
+    let data = slice.get_mapped_range();
+    data.chunks_exact(bytes_per_row as usize)
+        .flat_map(|row| {
+            row[..(texel_size * texture.width()) as usize].chunks_exact(texel_size as usize)
+        })
+        .map(|texel| match texture.format() {
+            TextureFormat::Rgba8Unorm => std::array::from_fn(|i| f32::from(texel[i]) / 255.),
+            _ => bytemuck::pod_read_unaligned(texel),
+        })
+        .collect()
+}
+
+/// Renders one 128x128 frame of the scene with and without the BVH, checks they match and
+/// writes the one with the BVH to `file`, returning its pixels.
+fn render_linear_and_bvh(
+    gpu_manager: &GpuManager,
+    scene: &Scene,
+    camera: &Camera,
+    render_settings: &RenderSettings,
+    file: &str,
+) -> Vec<[f32; 4]> {
+    let render = |bvh| {
+        let compute_ctx = ComputeContext::new(
+            gpu_manager.device(),
+            // Width must be a multiple of 128
+            (128, 128),
+            scene,
+            camera,
+            &RenderSettings {
+                bvh,
+                ..*render_settings
+            },
+        );
+        let mut encoder = gpu_manager
+            .device()
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Test Encoder"),
+            });
+        compute_ctx.draw(&mut encoder, gpu_manager.queue());
+        gpu_manager.queue().submit(Some(encoder.finish()));
+
+        if bvh {
+            assert!(
+                super::write_to_file(
+                    gpu_manager,
+                    &compute_ctx.previous_texture,
+                    Some(Path::new(file))
+                )
+                .is_ok()
+            );
+        }
+        read_texture(gpu_manager, &compute_ctx.previous_texture)
+    };
+
+    let (linear, bvh) = (render(false), render(true));
+    // Both find the same closest hits, so the same paths are traced, apart from the rare ray
+    // hitting two objects at the same distance.
+    let different = linear
+        .iter()
+        .zip(&bvh)
+        .filter(|(a, b)| a.iter().zip(b.iter()).any(|(a, b)| (a - b).abs() > 1e-3))
+        .count();
+    assert!(different < linear.len() / 100, "{different} pixels differ");
+    bvh
+}
+
+#[test]
+fn accumulation_counts_samples() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let render_settings = RenderSettings {
+        samples_per_pixel: 3,
+        max_ray_bounces: 8,
+        ..Default::default()
+    };
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        (64, 64),
+        &Scene::new(SPHERES.to_vec()),
+        &Camera::default(),
+        &render_settings,
+    );
+
+    for _ in 0..5 {
+        let mut encoder = gpu_manager
+            .device()
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Test Encoder"),
+            });
+        compute_ctx.draw(&mut encoder, gpu_manager.queue());
+        gpu_manager.queue().submit(Some(encoder.finish()));
+    }
+
+    // Frames alternate between the two textures, so the fifth one lands in `previous_texture`.
+    let texels = read_texture(&gpu_manager, &compute_ctx.previous_texture);
+    assert!(texels.iter().all(|texel| texel[3] == 15.));
+    assert!(
+        texels
+            .iter()
+            .all(|texel| texel[..3].iter().all(|c| c.is_finite() && *c >= 0.))
+    );
+}
+
+#[test]
+fn render_motion_blur_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let render = |sphere| {
+        let compute_ctx = ComputeContext::new(
+            gpu_manager.device(),
+            // Width must be a multiple of 128
+            (128, 128),
+            &Scene::new(vec![SPHERES[0], sphere]),
+            &Camera::default().with_shutter(0., 1.),
+            &RenderSettings::default(),
+        );
+
+        for _ in 0..10 {
+            let mut encoder =
+                gpu_manager
+                    .device()
+                    .create_command_encoder(&CommandEncoderDescriptor {
+                        label: Some("Test Encoder"),
+                    });
+            compute_ctx.draw(&mut encoder, gpu_manager.queue());
+            gpu_manager.queue().submit(Some(encoder.finish()));
+        }
+        compute_ctx
+    };
+
+    let material = material::Material::lambertian([0.1, 0.2, 0.5]);
+    let moving = render(Sphere::moving(
+        [-0.5, 0., -1.2],
+        [0.5, 0.2, -1.2],
+        0.3,
+        material,
+    ));
+    assert!(
+        super::write_to_file(
+            &gpu_manager,
+            &moving.output_texture,
+            Some(Path::new("multiple_frames_motion_blur_test.png"))
+        )
+        .is_ok()
+    );
+    let still = render(Sphere::new([0., 0.1, -1.2], 0.3, material));
+
+    let moving = read_texture(&gpu_manager, &moving.output_texture);
+    let still = read_texture(&gpu_manager, &still.output_texture);
+    // Mean color around the pixel.
+    let mean = |pixels: &[[f32; 4]], x: usize, y: usize| {
+        let (sum, samples) = (y - 2..y + 3)
+            .flat_map(|y| &pixels[y * 128 + x - 2..y * 128 + x + 3])
+            .fold((Vec3::ZERO, 0.), |(sum, samples), [r, g, b, a]| {
+                (sum + Vec3::new(*r, *g, *b), samples + a)
+            });
+        sum / samples
+    };
+
+    // The sphere covers the points a quarter, half and three quarters of the way along its
+    // path during about half of the exposure.
+    let sphere = mean(&still, 64, 59);
+    for (x, y) in [(51, 61), (64, 59), (77, 56)] {
+        // The sky only changes from row to row, and the sphere never gets this far left.
+        let sky = mean(&moving, 8, y);
+        let blend = (mean(&moving, x, y) - sky).dot(sphere - sky) / (sphere - sky).length_squared();
+        assert!(
+            (0.3..0.8).contains(&blend),
+            "the pixel at ({x}, {y}) is {blend} of the way from the sky to the sphere"
+        );
+    }
+}
+
+#[test]
+fn render_projections_to_files() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let render = |name: &str, scene: &Scene, camera: &Camera| {
+        let compute_ctx = ComputeContext::new(
+            gpu_manager.device(),
+            // Width must be a multiple of 128
+            (256, 128),
+            scene,
+            camera,
+            &RenderSettings::default(),
+        );
+
+        let mut encoder = gpu_manager
+            .device()
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Test Encoder"),
+            });
+        compute_ctx.draw(&mut encoder, gpu_manager.queue());
+        gpu_manager.queue().submit(Some(encoder.finish()));
+
+        assert!(
+            super::write_to_file(
+                &gpu_manager,
+                &compute_ctx.previous_texture,
+                Some(Path::new(&format!("one_frame_{name}_test.png")))
+            )
+            .is_ok()
+        );
+        read_texture(&gpu_manager, &compute_ctx.previous_texture)
+    };
+    let spheres = Scene::new(SPHERES.to_vec());
+    let camera = Camera::new([0., 0.5, 1.], [0., 0., -1.], [0., 1., 0.], 180.);
+
+    // Two spheres of the same size, the right one much further away.
+    let red = material::Material::lambertian([1., 0., 0.]);
+    let pixels = render(
+        "orthographic",
+        &Scene::new(vec![
+            Sphere::new([-1.5, 0., -2.], 0.5, red),
+            Sphere::new([1.5, 0., -8.], 0.5, red),
+        ]),
+        &Camera::default().with_projection(Projection::Orthographic { height: 3. }),
+    );
+    let width = |columns: std::ops::Range<usize>| {
+        columns
+            .filter(|&x| {
+                let [r, _, b, _] = pixels[64 * 128 * 2 + x];
+                r > 2. * b
+            })
+            .count()
+    };
+    let (near, far) = (width(0..128), width(128..256));
+    assert!(
+        near > 30 && near.abs_diff(far) <= 2,
+        "the spheres are {near} and {far} pixels wide"
+    );
+
+    // The corners are outside of the circle covering 180 degrees.
+    let pixels = render(
+        "fisheye",
+        &spheres,
+        &camera.with_projection(Projection::Fisheye),
+    );
+    assert_eq!(pixels[0][..3], [0.; 3]);
+    assert!(pixels[64 * 256 + 128][3] > 0.);
+
+    // The top row looks straight up, at the sky above every longitude.
+    let pixels = render(
+        "equirectangular",
+        &spheres,
+        &camera.with_projection(Projection::Equirectangular),
+    );
+    for (x, [r, g, b, samples]) in pixels[..256].iter().enumerate() {
+        assert!(
+            *b > 0.95 * samples && r < g && g < b,
+            "column {x} of the top row isn't sky"
+        );
+    }
+}
+
+#[test]
+fn sobol_sampler_has_lower_error() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let render = |sampler: Sampler, samples_per_pixel: u32, frames: u32| {
+        let compute_ctx = ComputeContext::new(
+            gpu_manager.device(),
+            (64, 64),
+            &Scene::new(SPHERES.to_vec()),
+            &Camera::default(),
+            &RenderSettings {
+                samples_per_pixel,
+                max_ray_bounces: 8,
+                sampler,
+                ..Default::default()
+            },
+        );
+        for _ in 0..frames {
+            let mut encoder =
+                gpu_manager
+                    .device()
+                    .create_command_encoder(&CommandEncoderDescriptor {
+                        label: Some("Test Encoder"),
+                    });
+            compute_ctx.draw(&mut encoder, gpu_manager.queue());
+            gpu_manager.queue().submit(Some(encoder.finish()));
+        }
+        let texture = if frames.is_multiple_of(2) {
+            &compute_ctx.output_texture
+        } else {
+            &compute_ctx.previous_texture
+        };
+        read_texture(&gpu_manager, texture)
+            .into_iter()
+            .map(|[r, g, b, samples]| [r / samples, g / samples, b / samples])
+            .collect::<Vec<_>>()
+    };
+
+    let reference = render(Sampler::Pcg, 128, 8);
+    let rmse = |image: Vec<[f32; 3]>| {
+        let squared_error: f32 = image
+            .iter()
+            .zip(&reference)
+            .flat_map(|(a, b)| a.iter().zip(b).map(|(a, b)| (a - b).powi(2)))
+            .sum();
+        (squared_error / (3 * image.len()) as f32).sqrt()
+    };
+
+    let pcg_error = rmse(render(Sampler::Pcg, 16, 1));
+    let sobol_error = rmse(render(Sampler::Sobol, 16, 1));
+    assert!(
+        sobol_error < pcg_error,
+        "Sobol error {sobol_error} isn't below PCG error {pcg_error}"
+    );
+}
+
+#[test]
+fn russian_roulette_is_unbiased() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let mean_brightness = |russian_roulette: Option<u32>| {
+        let compute_ctx = ComputeContext::new(
+            gpu_manager.device(),
+            (64, 64),
+            &Scene::new(SPHERES.to_vec()),
+            &Camera::default(),
+            &RenderSettings {
+                samples_per_pixel: 64,
+                russian_roulette,
+                ..Default::default()
+            },
+        );
+        for _ in 0..4 {
+            let mut encoder =
+                gpu_manager
+                    .device()
+                    .create_command_encoder(&CommandEncoderDescriptor {
+                        label: Some("Test Encoder"),
+                    });
+            compute_ctx.draw(&mut encoder, gpu_manager.queue());
+            gpu_manager.queue().submit(Some(encoder.finish()));
+        }
+        let texels = read_texture(&gpu_manager, &compute_ctx.output_texture);
+        texels
+            .iter()
+            .map(|[r, g, b, samples]| (r + g + b) / samples)
+            .sum::<f32>()
+            / texels.len() as f32
+    };
+
+    let without = mean_brightness(None);
+    let with = mean_brightness(Some(1));
+    assert!(
+        (with - without).abs() / without < 0.01,
+        "{with} with Russian roulette, {without} without"
+    );
+}
+
+#[test]
+fn adaptive_sampling_skips_converged_pixels() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let render_settings = RenderSettings {
+        samples_per_pixel: 8,
+        max_ray_bounces: 8,
+        adaptive_sampling: Some(AdaptiveSampling {
+            threshold: 0.05,
+            min_samples: 16,
+        }),
+        ..Default::default()
+    };
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        (64, 64),
+        &Scene::new(SPHERES.to_vec()),
+        &Camera::default(),
+        &render_settings,
+    );
+
+    for _ in 0..6 {
+        let mut encoder = gpu_manager
+            .device()
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Test Encoder"),
+            });
+        compute_ctx.draw(&mut encoder, gpu_manager.queue());
+        gpu_manager.queue().submit(Some(encoder.finish()));
+    }
+
+    // The sky converges almost immediately, while the diffuse and glass spheres keep sampling.
+    let texels = read_texture(&gpu_manager, &compute_ctx.output_texture);
+    assert!(texels.iter().all(|texel| texel[3] >= 16.));
+    assert!(texels.iter().any(|texel| texel[3] < 48.));
+    assert!(texels.iter().any(|texel| texel[3] == 48.));
+}
+
+#[test]
+fn render_mesh_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    // A red square in front of the camera, split into two triangles, on a mesh-only scene.
+    let square = Mesh::new(
+        &[
+            [-1., -1., -2.],
+            [1., -1., -2.],
+            [1., 1., -2.],
+            [-1., 1., -2.],
+        ],
+        vec![[0, 1, 2], [0, 2, 3]],
+        material::Material::lambertian([1., 0., 0.]),
+    );
+    // A smooth shaded octahedron in the middle of it.
+    let octahedron = Mesh::new(
+        &[
+            [0.3, 0., -1.5],
+            [-0.3, 0., -1.5],
+            [0., 0.3, -1.5],
+            [0., -0.3, -1.5],
+            [0., 0., -1.2],
+            [0., 0., -1.8],
+        ],
+        vec![
+            [0, 2, 4],
+            [2, 1, 4],
+            [1, 3, 4],
+            [3, 0, 4],
+            [2, 0, 5],
+            [1, 2, 5],
+            [3, 1, 5],
+            [0, 3, 5],
+        ],
+        material::Material::lambertian([0.1, 0.2, 0.5]),
+    )
+    .with_smooth_normals();
+
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        // Width must be a multiple of 128
+        (128, 128),
+        &Scene::default().with_mesh(square).with_mesh(octahedron),
+        &Camera::default(),
+        &RenderSettings::default(),
+    );
+
+    let mut encoder = gpu_manager
+        .device()
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Test Encoder"),
+        });
+    compute_ctx.draw(&mut encoder, gpu_manager.queue());
+    gpu_manager.queue().submit(Some(encoder.finish()));
+
+    let texels = read_texture(&gpu_manager, &compute_ctx.previous_texture);
+    let mean = |x: usize, y: usize| texels[y * 128 + x].map(|c| c / texels[y * 128 + x][3]);
+    // The square covers the corners of the image, the octahedron its center.
+    let corner = mean(40, 40);
+    assert!(corner[0] > 0.1 && corner[1] == 0. && corner[2] == 0.);
+    let center = mean(64, 64);
+    assert!(center[2] > center[0]);
+    assert!(mean(0, 0)[2] > 0.5);
+
+    assert!(
+        super::write_to_file(
+            &gpu_manager,
+            &compute_ctx.previous_texture,
+            Some(Path::new("one_frame_mesh_test.png"))
+        )
+        .is_ok()
+    );
+}
+
+#[test]
+fn load_obj_with_materials() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let directory = std::env::temp_dir().join("ray_load_obj_test");
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(
+        directory.join("materials.mtl"),
+        "newmtl red\nKd 1 0 0\n\n\
+         newmtl gold\nKd 0.1 0.1 0.1\nKs 0.8 0.6 0.2\nNs 198\n",
+    )
+    .unwrap();
+    std::fs::write(directory.join("glass.mtl"), "newmtl glass\nd 0.1\nNi 1.5\n").unwrap();
+    std::fs::write(
+        directory.join("model.obj"),
+        "mtllib materials.mtl glass.mtl\n\
+         v -1 -1 -2\nv 1 -1 -2\nv 1 1 -2\nv -1 1 -2\nv 0 0 -1.5\nvt 0 0\nvn 0 0 1\n\
+         usemtl red\nf 1//1 2//1 3//1 4//1\n\
+         usemtl gold\nf -5 -4 -1\n\
+         usemtl glass\nf 3/1 4/1 5/1\n\
+         usemtl red\nf 1 5 4\n",
+    )
+    .unwrap();
+
+    let scene = objects::load_obj(&directory.join("model.obj")).unwrap();
+    let materials: Vec<_> = scene.meshes.iter().map(|mesh| *mesh.material()).collect();
+    assert_eq!(
+        materials,
+        [
+            material::Material::lambertian([1., 0., 0.]),
+            material::Material::metal([0.8, 0.6, 0.2], (2f32 / 200.).sqrt()),
+            material::Material::dieletric(1.5),
+        ]
+    );
+    // The quad is split in two triangles.
+    assert_eq!(scene.buffers().triangles.len(), 5);
+
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        // Width must be a multiple of 128
+        (128, 128),
+        &scene,
+        &Camera::default(),
+        &RenderSettings::default(),
+    );
+
+    let mut encoder = gpu_manager
+        .device()
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Test Encoder"),
+        });
+    compute_ctx.draw(&mut encoder, gpu_manager.queue());
+    gpu_manager.queue().submit(Some(encoder.finish()));
+
+    assert!(
+        super::write_to_file(
+            &gpu_manager,
+            &compute_ctx.previous_texture,
+            Some(Path::new("one_frame_obj_test.png"))
+        )
+        .is_ok()
+    );
+}
+
+#[test]
+fn load_gltf_with_transforms_and_camera() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let directory = std::env::temp_dir().join("ray_load_gltf_test");
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("scene.gltf");
+    std::fs::write(
+        &path,
+        r#"{
+            "asset": { "version": "2.0" },
+            "extensionsUsed": ["KHR_materials_transmission", "KHR_materials_ior", "EXT_unknown"],
+            "extensionsRequired": ["EXT_unknown"],
+            "scene": 0,
+            "scenes": [{ "nodes": [0, 3] }],
+            "nodes": [
+                { "translation": [0, 0, -3], "children": [1, 2] },
+                { "mesh": 0, "translation": [-1, 0, 0] },
+                { "mesh": 1, "translation": [1, 0, 0], "scale": [0.5, 0.5, 0.5] },
+                { "camera": 0, "translation": [0, 0, 1] }
+            ],
+            "cameras": [{ "type": "perspective", "perspective": { "yfov": 1, "znear": 0.1 } }],
+            "meshes": [
+                { "primitives": [
+                    { "attributes": { "POSITION": 0 }, "material": 0 },
+                    { "attributes": { "POSITION": 0 }, "mode": 0 }
+                ] },
+                { "primitives": [{ "attributes": { "POSITION": 0 }, "material": 1 }] }
+            ],
+            "materials": [
+                {
+                    "pbrMetallicRoughness": {
+                        "baseColorFactor": [1, 0.8, 0.2, 1],
+                        "metallicFactor": 1,
+                        "roughnessFactor": 0.5,
+                        "baseColorTexture": { "index": 1 }
+                    },
+                    "normalTexture": { "index": 0 }
+                },
+                { "extensions": {
+                    "KHR_materials_transmission": { "transmissionFactor": 1 },
+                    "KHR_materials_ior": { "ior": 1.4 }
+                } }
+            ],
+            "textures": [{ "source": 0 }, { "source": 1 }],
+            "images": [
+                { "uri": "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR4nGP4z8DwHwAFAAH/iZk9HQAAAABJRU5ErkJggg==" },
+                { "uri": "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR4nGNg+M/wHwAEAQH/cetH5QAAAABJRU5ErkJggg==" }
+            ],
+            "accessors": [{
+                "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                "min": [-1, -1, 0], "max": [1, 1, 0]
+            }],
+            "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+            "buffers": [{
+                "byteLength": 36,
+                "uri": "data:application/octet-stream;base64,AACAvwAAgL8AAAAAAACAPwAAgL8AAAAAAAAAAAAAgD8AAAAA"
+            }]
+        }"#,
+    )
+    .unwrap();
+
+    let (scene, camera) = objects::load_gltf(&path).unwrap();
+    let materials: Vec<_> = scene
+        .geometries
+        .iter()
+        .flat_map(|geometry| geometry.meshes.iter().map(|mesh| *mesh.material()))
+        .collect();
+    // The primitive drawn as points is skipped.
+    assert_eq!(
+        materials,
+        [
+            material::Material::principled([1., 0.8, 0.2], 1., 0.5, 0.5)
+                .with_texture(Texture::Image(0)),
+            material::Material::dieletric(1.4),
+        ]
+    );
+    // Only the image of the base color texture is imported.
+    assert_eq!(scene.images.len(), 1);
+    assert_eq!(scene.images[0].as_raw(), &[0, 255, 0, 255]);
+    assert_eq!(
+        scene.instances,
+        [
+            Instance::new(0, Mat4::from_translation(Vec3::new(-1., 0., -3.))),
+            Instance::new(
+                1,
+                Mat4::from_scale_rotation_translation(
+                    Vec3::splat(0.5),
+                    Quat::IDENTITY,
+                    Vec3::new(1., 0., -3.)
+                )
+            ),
+        ]
+    );
+    let camera = camera.unwrap();
+    assert_eq!(camera.look_from, [0., 0., 1.]);
+    assert_eq!(camera.look_at, [0., 0., 0.]);
+    assert_eq!(camera.up, [0., 1., 0.]);
+    assert!((camera.vfov - 1f32.to_degrees()).abs() < 1e-4);
+
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        // Width must be a multiple of 128
+        (128, 128),
+        &scene,
+        &camera,
+        &RenderSettings::default(),
+    );
+
+    let mut encoder = gpu_manager
+        .device()
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Test Encoder"),
+        });
+    compute_ctx.draw(&mut encoder, gpu_manager.queue());
+    gpu_manager.queue().submit(Some(encoder.finish()));
+
+    assert!(
+        super::write_to_file(
+            &gpu_manager,
+            &compute_ctx.previous_texture,
+            Some(Path::new("one_frame_gltf_test.png"))
+        )
+        .is_ok()
+    );
+
+    // The world has no objects of its own, so its hierarchy is empty and must never be hit.
+    // The sky is seen in the corner of the bounds of the glass triangle, beside its apex.
+    let [r, _, b, _] = read_texture(&gpu_manager, &compute_ctx.previous_texture)[51 * 128 + 105];
+    assert!(b > r, "the bounds of the glass triangle are drawn");
+}
+
+#[test]
+fn load_obj_reports_line_numbers() {
+    let error = objects::load_obj(Path::new("missing.obj")).unwrap_err();
+    assert_eq!(error.to_string(), "Couldn't read missing.obj");
+
+    let directory = std::env::temp_dir().join("ray_invalid_obj_test");
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("model.obj");
+
+    std::fs::write(&path, "v 0 0 0\nv 1 0 0\nv 0 1 0\n\nf 1 2 4\n").unwrap();
+    let error = objects::load_obj(&path).unwrap_err();
+    assert_eq!(error.to_string(), format!("{}:5", path.display()));
+    assert_eq!(
+        error.root_cause().to_string(),
+        "The vertex index 4 is out of bounds, only 3 are defined."
+    );
+
+    std::fs::write(directory.join("materials.mtl"), "newmtl red\nKd 1 zero 0\n").unwrap();
+    std::fs::write(&path, "# A comment\nmtllib materials.mtl\n").unwrap();
+    let error = objects::load_obj(&path).unwrap_err();
+    assert_eq!(
+        format!("{error:#}"),
+        format!(
+            "{}:2: {}:2: Invalid number \"zero\".: invalid float literal",
+            path.display(),
+            directory.join("materials.mtl").display()
+        )
+    );
+}
+
+#[test]
+fn bvh_depth_is_bounded() {
+    // Spacing growing geometrically lets the surface area heuristic split off one sphere at a
+    // time.
+    let primitives = (0..200)
+        .map(|i| {
+            let center = Vec3::X * 1.5f32.powi(i);
+            let aabb = crate::bvh::Aabb::from_points([center - 0.1, center + 0.1]);
+            crate::bvh::Primitive::new(aabb, crate::bvh::SPHERE, i as usize)
+        })
+        .collect();
+    let mut nodes = Vec::new();
+    let root = crate::bvh::build(primitives, &mut nodes);
+
+    // Each node's `right_child` and `primitive`, as laid out for the shader.
+    let fields = |index: u32| {
+        let node: [u32; 8] = bytemuck::cast(nodes[index as usize]);
+        (node[3], node[7])
+    };
+    let mut stack = vec![(root, 0)];
+    let mut max_depth = 0;
+    while let Some((index, depth)) = stack.pop() {
+        max_depth = max_depth.max(depth);
+        let (right_child, primitive) = fields(index);
+        if primitive == u32::MAX {
+            stack.extend([(index + 1, depth + 1), (right_child, depth + 1)]);
+        }
+    }
+    assert!(
+        max_depth <= crate::bvh::MAX_DEPTH,
+        "leaves are {max_depth} levels deep"
+    );
+}
+
+#[test]
+fn bvh_matches_linear_search() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    // A grid of small spheres on the ground, some of them moving, in front of the mesh of a
+    // square.
+    let mut spheres = vec![SPHERES[0]];
+    for i in 0..400 {
+        let center = [
+            (i % 20) as f32 * 0.2 - 2.,
+            -0.4,
+            -0.2 * (i / 20) as f32 - 0.5,
+        ];
+        let material = [
+            material::Material::lambertian([0.1, 0.2, 0.5]),
+            material::Material::metal([0.8, 0.6, 0.2], 0.3),
+            material::Material::dieletric(1.5),
+        ][i % 3];
+        spheres.push(if i % 7 == 0 {
+            Sphere::moving(center, [center[0], -0.2, center[2]], 0.08, material)
+        } else {
+            Sphere::new(center, 0.08, material)
+        });
+    }
+    let square = Mesh::new(
+        &[
+            [-3., -1., -5.],
+            [3., -1., -5.],
+            [3., 2., -5.],
+            [-3., 2., -5.],
+        ],
+        vec![[0, 1, 2], [0, 2, 3]],
+        material::Material::lambertian([1., 0., 0.]),
+    );
+    let scene = Scene::new(spheres).with_mesh(square);
+    let camera = Camera::new([0., 0.5, 1.], [0., -0.4, -1.5], [0., 1., 0.], 60.);
+
+    render_linear_and_bvh(
+        &gpu_manager,
+        &scene,
+        &camera,
+        // Software renderers may stop the loops of invocations running too many iterations,
+        // which the linear search through hundreds of spheres would otherwise get close to.
+        &RenderSettings {
+            samples_per_pixel: 4,
+            ..Default::default()
+        },
+        "one_frame_bvh_test.png",
+    );
+}
+
+#[test]
+fn render_cornell_box_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let red = material::Material::lambertian([0.65, 0.05, 0.05]);
+    let green = material::Material::lambertian([0.12, 0.45, 0.15]);
+    let white = material::Material::lambertian([0.73, 0.73, 0.73]);
+    // Lit by the sky through its open front.
+    let scene = Scene::default()
+        .with_quads([
+            Quad::new([-1., -1., 0.], [0., 0., -2.], [0., 2., 0.], red),
+            Quad::new([1., -1., 0.], [0., 0., -2.], [0., 2., 0.], green),
+            Quad::new([-1., -1., -2.], [2., 0., 0.], [0., 2., 0.], white),
+            Quad::new([-1., -1., 0.], [2., 0., 0.], [0., 0., -2.], white),
+            Quad::new([-1., 1., 0.], [2., 0., 0.], [0., 0., -2.], white),
+        ])
+        .with_quads(Quad::cuboid([-0.5, -1., -1.5], [0.1, 0.2, -0.9], white));
+
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        // Width must be a multiple of 128
+        (128, 128),
+        &scene,
+        &Camera::new([0., 0., 2.4], [0., 0., -1.], [0., 1., 0.], 45.),
+        &RenderSettings::default(),
+    );
+
+    for _ in 0..4 {
+        let mut encoder = gpu_manager
+            .device()
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Test Encoder"),
+            });
+        compute_ctx.draw(&mut encoder, gpu_manager.queue());
+        gpu_manager.queue().submit(Some(encoder.finish()));
+    }
+
+    let texels = read_texture(&gpu_manager, &compute_ctx.output_texture);
+    let mean = |x: usize, y: usize| {
+        let mut sum = [0.; 4];
+        for texel in (y - 2..=y + 2).flat_map(|y| &texels[y * 128 + x - 2..=y * 128 + x + 2]) {
+            sum = std::array::from_fn(|i| sum[i] + texel[i]);
+        }
+        sum.map(|c| c / sum[3])
+    };
+    let (left, right) = (mean(8, 64), mean(119, 64));
+    assert!(left[0] > 2. * left[1], "left wall isn't red: {left:?}");
+    assert!(
+        right[1] > 2. * right[0],
+        "right wall isn't green: {right:?}"
+    );
+
+    assert!(
+        super::write_to_file(
+            &gpu_manager,
+            &compute_ctx.output_texture,
+            Some(Path::new("cornell_box_test.png"))
+        )
+        .is_ok()
+    );
+}
+
+#[test]
+fn render_instances_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let mut scene = Scene::default().with_quads([Quad::new(
+        [-10., -0.5, 10.],
+        [20., 0., 0.],
+        [0., 0., -20.],
+        material::Material::lambertian([0.8, 0.8, 0.]),
+    )]);
+    let ellipsoid = scene.add_geometry(Geometry {
+        spheres: vec![Sphere::new(
+            [0., 0., 0.],
+            1.,
+            material::Material::lambertian([0.1, 0.2, 0.5]),
+        )],
+        ..Default::default()
+    });
+    let crate_box = scene.add_geometry(Geometry {
+        quads: Quad::cuboid(
+            [-0.5, -0.5, -0.5],
+            [0.5, 0.5, 0.5],
+            material::Material::metal([0.8, 0.6, 0.2], 0.3),
+        )
+        .to_vec(),
+        ..Default::default()
+    });
+
+    // A squashed sphere in the middle, surrounded by many rotated copies of the same box.
+    scene.instances.push(Instance::new(
+        ellipsoid,
+        Mat4::from_translation(Vec3::new(0., -0.2, -1.5))
+            * Mat4::from_scale(Vec3::new(0.6, 0.3, 0.3)),
+    ));
+    for i in 0..100 {
+        let position = Vec3::new(
+            (i % 10) as f32 * 0.4 - 1.8,
+            -0.4,
+            -0.4 * (i / 10) as f32 - 2.,
+        );
+        scene.instances.push(Instance::new(
+            crate_box,
+            Mat4::from_scale_rotation_translation(
+                Vec3::splat(0.15),
+                Quat::from_rotation_y(i as f32),
+                position,
+            ),
+        ));
+    }
+    let camera = Camera::new([0., 0.5, 1.], [0., -0.2, -1.5], [0., 1., 0.], 60.);
+
+    // Few and short paths, since the search without the BVH tests every instance.
+    let bvh = render_linear_and_bvh(
+        &gpu_manager,
+        &scene,
+        &camera,
+        &RenderSettings {
+            samples_per_pixel: 4,
+            max_ray_bounces: 4,
+            ..Default::default()
+        },
+        "one_frame_instances_test.png",
+    );
+
+    // The ellipsoid is wider than it is tall.
+    let blue = |texel: &[f32; 4]| texel[2] > 2. * texel[0];
+    let row = bvh[64 * 128..65 * 128]
+        .iter()
+        .filter(|texel| blue(texel))
+        .count();
+    let column = (0..128).filter(|y| blue(&bvh[y * 128 + 64])).count();
+    assert!(row > 2 * column, "{row} {column}");
+}
+
+#[test]
+fn render_instances_only_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    // Nothing outside of instances, as imported from glTF, and an instance of nothing.
+    let mut scene = Scene::default();
+    let ground = scene.add_geometry(Geometry {
+        quads: vec![Quad::new(
+            [-10., -0.5, 10.],
+            [20., 0., 0.],
+            [0., 0., -20.],
+            material::Material::lambertian([0.8, 0.8, 0.]),
+        )],
+        ..Default::default()
+    });
+    let ellipsoid = scene.add_geometry(Geometry {
+        spheres: vec![Sphere::new(
+            [0., 0., 0.],
+            1.,
+            material::Material::lambertian([0.1, 0.2, 0.5]),
+        )],
+        ..Default::default()
+    });
+    let nothing = scene.add_geometry(Geometry::default());
+    let scene = scene.with_instances([
+        Instance::new(ground, Mat4::IDENTITY),
+        Instance::new(
+            ellipsoid,
+            Mat4::from_translation(Vec3::new(0., -0.2, -1.5))
+                * Mat4::from_scale(Vec3::new(0.6, 0.3, 0.3)),
+        ),
+        Instance::new(nothing, Mat4::from_translation(Vec3::new(0., 0., -1.))),
+    ]);
+    let camera = Camera::new([0., 0.5, 1.], [0., -0.2, -1.5], [0., 1., 0.], 60.);
+
+    let bvh = render_linear_and_bvh(
+        &gpu_manager,
+        &scene,
+        &camera,
+        &RenderSettings::default(),
+        "one_frame_instances_only_test.png",
+    );
+
+    let [r, _, b, _] = bvh[64 * 128 + 64];
+    assert!(b > 2. * r, "the ellipsoid is missing");
+    let [r, _, b, _] = bvh[120 * 128 + 64];
+    assert!(r > b, "the ground is missing");
+    let [r, _, b, _] = bvh[10 * 128 + 64];
+    assert!(b > r, "the sky is missing");
+}
+
+#[test]
+fn render_medium_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    // Renders an even number of frames, which ends up in the output texture.
+    let render = |scene: &Scene, camera: &Camera| {
+        let compute_ctx = ComputeContext::new(
+            gpu_manager.device(),
+            // Width must be a multiple of 128
+            (128, 128),
+            scene,
+            camera,
+            &RenderSettings {
+                samples_per_pixel: 16,
+                max_ray_bounces: 8,
+                ..Default::default()
+            },
+        );
+        for _ in 0..16 {
+            let mut encoder =
+                gpu_manager
+                    .device()
+                    .create_command_encoder(&CommandEncoderDescriptor {
+                        label: Some("Test Encoder"),
+                    });
+            compute_ctx.draw(&mut encoder, gpu_manager.queue());
+            gpu_manager.queue().submit(Some(encoder.finish()));
+        }
+        compute_ctx
+    };
+
+    // Light crossing the middle of the absorbing sphere travels through one unit of it, so
+    // half of it should get through.
+    let absorbing = Sphere::new(
+        [0., 0., -1.5],
+        0.5,
+        material::Material::medium([0.; 3], 2f32.ln(), 0.),
+    );
+    let smoke = [
+        Sphere::new(
+            [-1.1, 0., -1.5],
+            0.4,
+            material::Material::medium([0.9; 3], 4., 0.),
+        ),
+        Sphere::new(
+            [1.1, 0., -1.5],
+            0.4,
+            material::Material::medium([0.9; 3], 4., 0.8),
+        ),
+    ];
+    let with_medium = render(
+        &Scene::new([SPHERES[0], absorbing].into_iter().chain(smoke).collect()),
+        &Camera::default(),
+    );
+    let without_medium = render(&Scene::new(vec![SPHERES[0]]), &Camera::default());
+
+    let center_brightness = |compute_ctx: &ComputeContext| {
+        let texels = read_texture(&gpu_manager, &compute_ctx.output_texture);
+        let [r, g, b, samples] = (62..66)
+            .flat_map(|y| &texels[y * 128 + 62..y * 128 + 66])
+            .fold([0.; 4], |sum, texel| {
+                std::array::from_fn(|i| sum[i] + texel[i])
+            });
+        (r + g + b) / samples
+    };
+    let transmittance = center_brightness(&with_medium) / center_brightness(&without_medium);
+    assert!((transmittance - 0.5).abs() < 0.05, "{transmittance}");
+
+    // A light inside the absorbing medium is dimmed by the medium between it and the camera,
+    // from the sphere's boundary or from the camera inside it.
+    let light = Sphere::new(
+        [0., 0., -1.5],
+        0.1,
+        material::Material::emissive([1.; 3], 1.),
+    );
+    let fog = Sphere::new(
+        [0., 0., -1.5],
+        0.5,
+        material::Material::medium([0.; 3], 2f32.ln() / 0.4, 0.),
+    );
+    for (camera, expected) in [
+        (Camera::default(), 0.5),
+        (
+            Camera::new([0., 0., -1.2], [0., 0., -2.], [0., 1., 0.], 90.),
+            0.5f32.sqrt(),
+        ),
+    ] {
+        let transmittance = center_brightness(&render(&Scene::new(vec![fog, light]), &camera))
+            / center_brightness(&render(&Scene::new(vec![light]), &camera));
+        assert!(
+            (transmittance - expected).abs() < 0.05,
+            "{transmittance} of the light gets through instead of {expected}"
+        );
+    }
+
+    assert!(
+        super::write_to_file(
+            &gpu_manager,
+            &with_medium.output_texture,
+            Some(Path::new("medium_test.png"))
+        )
+        .is_ok()
+    );
+}
+
+#[test]
+fn render_planes_and_disks_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let ground = Plane::new(
+        [0., -0.5, 0.],
+        [0., 1., 0.],
+        material::Material::lambertian([0.8, 0.8, 0.]),
+    );
+    let mut scene = Scene::default().with_planes([ground]).with_disks([
+        Disk::new(
+            [0., 0., -2.],
+            [0., 0., 1.],
+            0.4,
+            material::Material::lambertian([0.65, 0.05, 0.05]),
+        ),
+        Disk::new(
+            [1., 0., -2.],
+            [-1., 1., 1.],
+            0.4,
+            material::Material::metal([0.8, 0.8, 0.8], 0.),
+        ),
+    ]);
+    // A disk squashed into an ellipse.
+    let disk = scene.add_geometry(Geometry {
+        disks: vec![Disk::new(
+            [0., 0., 0.],
+            [0., 0., 1.],
+            0.4,
+            material::Material::lambertian([0.12, 0.45, 0.15]),
+        )],
+        ..Default::default()
+    });
+    let scene = scene.with_instances([Instance::new(
+        disk,
+        Mat4::from_translation(Vec3::new(-1., 0., -2.)) * Mat4::from_scale(Vec3::new(1., 0.5, 1.)),
+    )]);
+
+    let bvh = render_linear_and_bvh(
+        &gpu_manager,
+        &scene,
+        &Camera::default(),
+        &RenderSettings::default(),
+        "planes_and_disks_test.png",
+    );
+
+    // The horizon of a plane is straight and level with the camera, in the middle row.
+    for x in [0, 8, 119, 127] {
+        let ([r, _, b, _], [ground_r, _, ground_b, _]) = (bvh[61 * 128 + x], bvh[66 * 128 + x]);
+        assert!(b > r, "no sky above the horizon at column {x}");
+        assert!(
+            ground_r > ground_b,
+            "no ground below the horizon at column {x}"
+        );
+    }
+}
+
+#[test]
+fn render_shapes_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let scene = Scene::default()
+        .with_planes([Plane::new(
+            [0., -0.5, 0.],
+            [0., 1., 0.],
+            material::Material::lambertian([0.8, 0.8, 0.]),
+        )])
+        .with_cylinders([Cylinder::new(
+            [-0.9, -0.5, -2.5],
+            [-0.9, 0.4, -2.5],
+            0.3,
+            material::Material::lambertian([0.12, 0.45, 0.15]),
+        )])
+        .with_cones([
+            Cone::new(
+                [0., -0.5, -2.5],
+                [0., 0.5, -2.5],
+                0.35,
+                material::Material::metal([0.8, 0.6, 0.2], 0.2),
+            ),
+            Cone::frustum(
+                [0.9, -0.5, -2.5],
+                0.3,
+                [0.9, -0.2, -2.5],
+                0.15,
+                material::Material::dieletric(1.5),
+            ),
+        ])
+        .with_tori([Torus::new(
+            [0.9, 0.3, -2.5],
+            [0., 0., 1.],
+            0.4,
+            0.12,
+            material::Material::lambertian([0.65, 0.05, 0.05]),
+        )]);
+
+    let bvh = render_linear_and_bvh(
+        &gpu_manager,
+        &scene,
+        &Camera::default(),
+        &RenderSettings::default(),
+        "shapes_test.png",
+    );
+
+    // The sky is seen through the hole of the torus, facing the camera.
+    let [r, _, b, _] = bvh[56 * 128 + 87];
+    assert!(b > r, "the torus' hole isn't empty");
+    let [r, _, b, _] = bvh[56 * 128 + 97];
+    assert!(r > b, "the torus' ring is missing");
+}
+
+#[test]
+fn render_csg_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let glass = material::Material::dieletric(1.5);
+    let red = material::Material::lambertian([0.65, 0.05, 0.05]);
+    let metal = material::Material::metal([0.8, 0.6, 0.2], 0.2);
+    let scene = Scene::default()
+        .with_planes([Plane::new(
+            [0., -0.5, 0.],
+            [0., 1., 0.],
+            material::Material::lambertian([0.8, 0.8, 0.]),
+        )])
+        .with_csg([
+            // A biconvex lens.
+            Csg::from(Sphere::new([-1., 0., -2.1], 0.6, glass)).intersection(Sphere::new(
+                [-1., 0., -2.9],
+                0.6,
+                glass,
+            )),
+            // A sphere with a square tunnel through it, facing the camera.
+            Csg::from(Sphere::new([0., 0., -2.5], 0.5, red)).difference(Csg::cuboid(
+                [-0.15, -0.15, -3.5],
+                [0.15, 0.15, -1.5],
+                red,
+            )),
+            // A ring on a stand.
+            Csg::from(Torus::new([1., 0.1, -2.5], [0., 0., 1.], 0.3, 0.08, metal)).union(
+                Cylinder::new([1., -0.5, -2.5], [1., -0.2, -2.5], 0.05, metal),
+            ),
+        ]);
+
+    let bvh = render_linear_and_bvh(
+        &gpu_manager,
+        &scene,
+        &Camera::default(),
+        &RenderSettings::default(),
+        "csg_test.png",
+    );
+
+    // The ground is seen through the tunnel, but not through the rest of the sphere.
+    let [r, g, _, _] = bvh[64 * 128 + 64];
+    assert!(g > r / 2., "the tunnel isn't empty");
+    let [r, g, _, _] = bvh[73 * 128 + 64];
+    assert!(g < r / 2., "the sphere is missing");
+}
+
+#[test]
+fn render_csg_with_torus_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    // A sphere with a groove carved by a torus lying on its surface, facing the camera.
+    let red = material::Material::lambertian([0.65, 0.05, 0.05]);
+    let green = material::Material::lambertian([0.12, 0.45, 0.15]);
+    let scene = Scene::default().with_csg([Csg::from(Sphere::new([0., 0., -2.5], 0.5, red))
+        .difference(Torus::new([0., 0., -2.1], [0., 0., 1.], 0.3, 0.1, green))]);
+
+    let bvh = render_linear_and_bvh(
+        &gpu_manager,
+        &scene,
+        &Camera::default(),
+        &RenderSettings::default(),
+        "csg_torus_test.png",
+    );
+
+    // Rays enter the torus, then the sphere, and only hit the solid when leaving the torus.
+    let [r, g, _, _] = bvh[64 * 128 + 64];
+    assert!(r > 2. * g, "the sphere is missing");
+    let [r, g, _, _] = bvh[64 * 128 + 73];
+    assert!(g > r, "the groove is missing");
+    // Crossing the torus twice at the same point would let the sky through the sphere.
+    let holes = (0..128 * 128usize)
+        .filter(|i| (i / 128).abs_diff(64).pow(2) + (i % 128).abs_diff(64).pow(2) < 121)
+        .filter(|&i| {
+            let [r, g, b, _] = bvh[i];
+            b > r && b > g
+        })
+        .count();
+    assert!(holes < 5, "{holes} pixels of sky within the sphere");
+}
+
+#[test]
+fn render_sdfs_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let red = material::Material::lambertian([0.65, 0.05, 0.05]);
+    let metal = material::Material::metal([0.8, 0.6, 0.2], 0.2);
+    let scene = Scene::default()
+        .with_planes([Plane::new(
+            [0., -0.5, 0.],
+            [0., 1., 0.],
+            material::Material::lambertian([0.8, 0.8, 0.]),
+        )])
+        .with_sdfs([
+            // Three snowmen, whose spheres don't touch but are blended together.
+            Sdf::sphere([0., 0., -2.5], 0.2, red)
+                .smooth_union(Sdf::sphere([0., 0.35, -2.5], 0.1, red), 0.15)
+                .repeat([0.7, 0., 0.], [1, 0, 0]),
+            // A tile in a ring.
+            Sdf::cuboid([-0.15, -0.45, -2.65], [0.15, -0.4, -2.35], metal).smooth_union(
+                Sdf::torus([0., -0.42, -2.5], [0., 1., 0.], 0.3, 0.05, metal),
+                0.05,
+            ),
+        ]);
+
+    let bvh = render_linear_and_bvh(
+        &gpu_manager,
+        &scene,
+        &Camera::default(),
+        &RenderSettings::default(),
+        "sdfs_test.png",
+    );
+
+    // The gap between the spheres is filled by the blend.
+    let [r, g, _, _] = bvh[58 * 128 + 64];
+    assert!(g < r / 2., "the spheres aren't blended");
+    // There's a copy on each side, but no further.
+    let [r, g, _, _] = bvh[64 * 128 + 82];
+    assert!(g < r / 2., "the repetition is missing");
+    let [r, g, _, _] = bvh[64 * 128 + 100];
+    assert!(g > r / 2., "the repetition isn't limited");
+}
+
+#[test]
+fn render_repeated_sdf_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    // A sphere right of the origin, copied once on each side of it.
+    let red = material::Material::lambertian([0.65, 0.05, 0.05]);
+    let scene = Scene::default()
+        .with_sdfs([Sdf::sphere([0.9, 0., -3.], 0.2, red).repeat([0.6, 0., 0.], [1, 0, 0])]);
+
+    let bvh = render_linear_and_bvh(
+        &gpu_manager,
+        &scene,
+        &Camera::default(),
+        &RenderSettings::default(),
+        "repeated_sdf_test.png",
+    );
+
+    // Copies at x = 0.3, 0.9 and 1.5, and none at -0.3 and 2.1.
+    for x in [70, 83, 96] {
+        let [r, _, b, _] = bvh[64 * 128 + x];
+        assert!(r > b, "no copy at column {x}");
+    }
+    for x in [58, 109] {
+        let [r, _, b, _] = bvh[64 * 128 + x];
+        assert!(b > r, "extra copy at column {x}");
+    }
+}
+
+#[test]
+fn render_emissive_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let scene = Scene::new(vec![
+        Sphere::new(
+            [0., -100.5, -1.5],
+            100.,
+            material::Material::lambertian([0.8, 0.8, 0.8]),
+        ),
+        Sphere::new(
+            [0., 0., -1.5],
+            0.3,
+            material::Material::emissive([1., 0.5, 0.25], 4.),
+        ),
+    ]);
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        // Width must be a multiple of 128
+        (128, 128),
+        &scene,
+        &Camera::default(),
+        &RenderSettings {
+            background: Background::Color([0.; 3]),
+            ..Default::default()
+        },
+    );
+
+    let mut encoder = gpu_manager
+        .device()
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Test Encoder"),
+        });
+    compute_ctx.draw(&mut encoder, gpu_manager.queue());
+    gpu_manager.queue().submit(Some(encoder.finish()));
+
+    assert!(
+        super::write_to_file(
+            &gpu_manager,
+            &compute_ctx.previous_texture,
+            Some(Path::new("emissive_test.png"))
+        )
+        .is_ok()
+    );
+    let pixels = read_texture(&gpu_manager, &compute_ctx.previous_texture);
+
+    // The light is seen directly, with its full radiance.
+    let [r, g, b, samples] = pixels[64 * 128 + 64];
+    assert!(samples > 0.);
+    for (value, expected) in [r, g, b].into_iter().zip([4., 2., 1.]) {
+        assert!(
+            (value / samples - expected).abs() < 1e-3,
+            "{value} / {samples}"
+        );
+    }
+    // Nothing else lights the scene.
+    assert_eq!(pixels[0][..3], [0.; 3]);
+    // The ground under the light is lit by it.
+    let lit = (90..110)
+        .flat_map(|y| (54..74).map(move |x| y * 128 + x))
+        .filter(|&i| pixels[i][..3].iter().all(|&value| value > 0.))
+        .count();
+    assert!(lit > 40, "only {lit} pixels of the ground are lit");
+}
+
+#[test]
+fn render_textures_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let mut scene = Scene::default();
+    // Red, green, blue and white quarters, from the top left.
+    let quarters = scene.add_image(image::RgbaImage::from_fn(2, 2, |x, y| {
+        image::Rgba(match (x, y) {
+            (0, 0) => [255, 0, 0, 255],
+            (1, 0) => [0, 255, 0, 255],
+            (0, 1) => [0, 0, 255, 255],
+            _ => [255; 4],
+        })
+    }));
+    // Stretched to the size of the largest image.
+    let magenta = scene.add_image(image::RgbaImage::from_pixel(
+        1,
+        1,
+        image::Rgba([255, 0, 255, 255]),
+    ));
+
+    let square = Mesh::new(
+        &[
+            [-1., -1., -2.],
+            [1., -1., -2.],
+            [1., 1., -2.],
+            [-1., 1., -2.],
+        ],
+        vec![[0, 1, 2], [0, 2, 3]],
+        material::Material::lambertian([1.; 3]).with_texture(Texture::Image(quarters)),
+    )
+    .with_uvs(&[[0., 0.], [1., 0.], [1., 1.], [0., 1.]]);
+    let scene = Scene {
+        spheres: vec![Sphere::new(
+            [0., 0., -1.5],
+            0.2,
+            material::Material::lambertian([1.; 3]).with_texture(Texture::Image(magenta)),
+        )],
+        ..scene
+    }
+    .with_mesh(square);
+
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        // Width must be a multiple of 128
+        (128, 128),
+        &scene,
+        &Camera::default(),
+        &RenderSettings {
+            background: Background::Color([1.; 3]),
+            ..Default::default()
+        },
+    );
+
+    let mut encoder = gpu_manager
+        .device()
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Test Encoder"),
+        });
+    compute_ctx.draw(&mut encoder, gpu_manager.queue());
+    gpu_manager.queue().submit(Some(encoder.finish()));
+
+    assert!(
+        super::write_to_file(
+            &gpu_manager,
+            &compute_ctx.previous_texture,
+            Some(Path::new("textures_test.png"))
+        )
+        .is_ok()
+    );
+    let pixels = read_texture(&gpu_manager, &compute_ctx.previous_texture);
+
+    // The middle of each quarter of the square, whose albedo is the texture's.
+    for ((x, y), expected) in [
+        ((48, 48), [1., 0., 0.]),
+        ((80, 48), [0., 1., 0.]),
+        ((48, 80), [0., 0., 1.]),
+        ((80, 80), [1., 1., 1.]),
+    ] {
+        let [r, g, b, samples] = pixels[y * 128 + x];
+        for (value, expected) in [r, g, b].into_iter().zip(expected) {
+            assert!(
+                (value / samples - expected).abs() < 0.2,
+                "({x}, {y}) is {:?}",
+                [r, g, b].map(|value| value / samples)
+            );
+        }
+    }
+    let [r, g, b, _] = pixels[64 * 128 + 64];
+    assert!(g < r / 2. && g < b / 2., "the sphere isn't magenta");
+}
+
+#[test]
+fn images_larger_than_textures_are_shrunk() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let mut scene = Scene::new(SPHERES.to_vec());
+    let width = gpu_manager.device().limits().max_texture_dimension_2d + 1;
+    scene.add_image(image::RgbaImage::from_pixel(
+        width,
+        1,
+        image::Rgba([255; 4]),
+    ));
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        (128, 128),
+        &scene,
+        &Camera::default(),
+        &RenderSettings::default(),
+    );
+
+    let mut encoder = gpu_manager
+        .device()
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Test Encoder"),
+        });
+    compute_ctx.draw(&mut encoder, gpu_manager.queue());
+    gpu_manager.queue().submit(Some(encoder.finish()));
+    gpu_manager.device().poll(wgpu::PollType::Wait).unwrap();
+}
+
+#[test]
+fn render_procedural_textures_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    // A sixth of the view for each texture, from the top left.
+    let textures = [
+        Texture::Checker {
+            size: 0.5,
+            color: [0.; 3],
+        },
+        Texture::Turbulence {
+            size: 0.25,
+            color: [0.; 3],
+        },
+        Texture::Marble {
+            size: 0.1,
+            color: [0.; 3],
+        },
+        Texture::Wood {
+            size: 0.2,
+            color: [0.; 3],
+        },
+        Texture::Noise {
+            size: 0.25,
+            color: [0.; 3],
+        },
+    ];
+    let quads = textures.iter().enumerate().map(|(i, &texture)| {
+        let corner = [(i % 3) as f32 * 4. / 3. - 2., -2. * (i / 3) as f32];
+        Quad::new(
+            [corner[0], corner[1], -2.],
+            [4. / 3., 0., 0.],
+            [0., 2., 0.],
+            material::Material::lambertian([1.; 3]).with_texture(texture),
+        )
+    });
+
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        // Width must be a multiple of 128
+        (128, 128),
+        &Scene::default().with_quads(quads),
+        &Camera::default(),
+        &RenderSettings {
+            background: Background::Color([1.; 3]),
+            ..Default::default()
+        },
+    );
+
+    let mut encoder = gpu_manager
+        .device()
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Test Encoder"),
+        });
+    compute_ctx.draw(&mut encoder, gpu_manager.queue());
+    gpu_manager.queue().submit(Some(encoder.finish()));
+
+    assert!(
+        super::write_to_file(
+            &gpu_manager,
+            &compute_ctx.previous_texture,
+            Some(Path::new("procedural_textures_test.png"))
+        )
+        .is_ok()
+    );
+    let pixels = read_texture(&gpu_manager, &compute_ctx.previous_texture);
+    let gray = |x: usize, y: usize| {
+        let [r, g, b, samples] = pixels[y * 128 + x];
+        (r + g + b) / (3. * samples)
+    };
+
+    // Neighbouring squares of the checkerboard have both colors.
+    assert!(gray(8, 8) < 0.01 && gray(24, 8) > 0.99 && gray(8, 24) > 0.99);
+    // The other patterns span most of the range between both colors.
+    for (x, y) in [(43, 0), (86, 0), (0, 64), (43, 64)] {
+        let (darkest, lightest) = (y..y + 64)
+            .flat_map(|y| (x..x + 42).map(move |x| (x, y)))
+            .map(|(x, y)| gray(x, y))
+            .fold((f32::MAX, f32::MIN), |(darkest, lightest), gray| {
+                (darkest.min(gray), lightest.max(gray))
+            });
+        assert!(lightest - darkest > 0.5, "({x}, {y}) has no pattern");
+    }
+    // Signed noise is as often above as below the middle of both colors.
+    let mean = (64..128)
+        .flat_map(|y| (43..85).map(move |x| (x, y)))
+        .map(|(x, y)| gray(x, y))
+        .sum::<f32>()
+        / (64. * 42.);
+    assert!((mean - 0.5).abs() < 0.15, "the noise has a mean of {mean}");
+}
+
+#[test]
+fn render_principled_to_file() {
+    let gpu_manager = GpuManager::simple().block_on().unwrap();
+
+    let scene = Scene::new(vec![
+        Sphere::new(
+            [-0.9, 0., -2.],
+            0.4,
+            material::Material::principled([1.; 3], 1., 0.5, 0.5),
+        ),
+        Sphere::new(
+            [0., 0., -2.],
+            0.4,
+            material::Material::principled([1.; 3], 0., 0.3, 0.5),
+        ),
+        Sphere::new(
+            [0.9, 0., -2.],
+            0.4,
+            material::Material::principled([1., 0.78, 0.34], 1., 0.2, 0.5),
+        ),
+    ]);
+    // A white furnace, where surfaces conserving energy disappear.
+    let compute_ctx = ComputeContext::new(
+        gpu_manager.device(),
+        // Width must be a multiple of 128
+        (128, 128),
+        &scene,
+        &Camera::default(),
+        &RenderSettings {
+            background: Background::Color([1.; 3]),
+            ..Default::default()
+        },
+    );
+
+    let mut encoder = gpu_manager
+        .device()
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Test Encoder"),
+        });
+    compute_ctx.draw(&mut encoder, gpu_manager.queue());
+    gpu_manager.queue().submit(Some(encoder.finish()));
+
+    assert!(
+        super::write_to_file(
+            &gpu_manager,
+            &compute_ctx.previous_texture,
+            Some(Path::new("principled_test.png"))
+        )
+        .is_ok()
+    );
+    let pixels = read_texture(&gpu_manager, &compute_ctx.previous_texture);
+    // Mean color around the center of the sphere in the column.
+    let mean = |x: usize| {
+        let (sum, samples) = (62..67)
+            .flat_map(|y| &pixels[y * 128 + x - 2..y * 128 + x + 3])
+            .fold((Vec3::ZERO, 0.), |(sum, samples), [r, g, b, a]| {
+                (sum + Vec3::new(*r, *g, *b), samples + a)
+            });
+        sum / samples
+    };
+
+    // White surfaces reflect all the light, except what single scattering between microfacets
+    // misses on rough ones.
+    for x in [35, 64] {
+        let mean = mean(x);
+        assert!(
+            mean.min_element() > 0.85 && mean.max_element() < 1.02,
+            "the sphere at {x} reflects {mean}"
+        );
+    }
+    // Metals facing the camera reflect their base color.
+    let gold = mean(93);
+    assert!(
+        (gold - Vec3::new(1., 0.78, 0.34)).abs().max_element() < 0.1,
+        "the gold sphere reflects {gold}"
+    );
+}